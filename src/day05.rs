@@ -4,6 +4,8 @@ use anyhow::{Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
+use crate::utils::graph::Graph;
+
 type Rules = Vec<(u32, u32)>;
 type Updates = Vec<Vec<u32>>;
 
@@ -67,48 +69,39 @@ fn part1((rules, updates): &(Rules, Updates)) -> u32 {
 
 
 #[aoc(day5, part2)]
-fn part2((rules, updates): &(Rules, Updates)) -> u32 {
+fn part2((rules, updates): &(Rules, Updates)) -> Result<u32> {
     updates
         .iter()
-        .filter_map(|update| {
+        .map(|update| {
             let pages = update.iter().copied().collect::<HashSet<_>>();
 
-            let rules = rules
+            let mut graph = rules
                 .iter()
                 .filter(|(l, r)| pages.contains(l) && pages.contains(r))
-                .collect_vec();
-
-            let mut remaining_pages = update.iter().copied().collect_vec();
-            let mut ordered_pages = vec![];
-
-            while !remaining_pages.is_empty() {
-                let next_page = *remaining_pages
-                    .iter()
-                    .find(|p| {
-                        rules
-                            .iter()
-                            .filter(|(_, r)| r == *p)
-                            .all(|(l, _)| ordered_pages.contains(l))
-                    })
-                    .expect("Unreachable: There should always be exactly one");
-
-                ordered_pages.push(next_page);
-                remaining_pages.retain(|p| p != &next_page);
+                .copied()
+                .collect::<Graph<_>>();
+
+            for page in update {
+                graph.add_node(*page);
             }
 
+            let ordered_pages = graph.topological_sort()?;
+
             if update.iter().eq(ordered_pages.iter()) {
-                return None;
+                return Ok(None);
             }
 
-            ordered_pages.get(ordered_pages.len() / 2).cloned()
+            Ok(ordered_pages.get(ordered_pages.len() / 2).cloned())
         })
-        .sum()
+        .process_results(|middle_pages| middle_pages.flatten().sum())
 }
 
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
 
+    use crate::utils::graph::Cycle;
+
     use super::*;
 
     const EXAMPLE1: &str = indoc! {"
@@ -154,11 +147,41 @@ mod tests {
 
     #[test]
     fn part2_example1() {
-        assert_eq!(123, part2(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(123, part2(&parse(EXAMPLE1).unwrap()).unwrap());
     }
 
     #[test]
     fn part2_input() {
-        assert_eq!(4145, part2(&parse(include_str!("../input/2024/day5.txt")).unwrap()));
+        assert_eq!(4145, part2(&parse(include_str!("../input/2024/day5.txt")).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn part2_cyclic_rules() {
+        let input = indoc! {"
+            1|2
+            2|3
+            3|1
+
+            3,2,1
+        "};
+
+        let error = part2(&parse(input).unwrap()).unwrap_err();
+        assert_eq!(Some(&Cycle(vec![2, 3, 1])), error.downcast_ref::<Cycle<u32>>());
+    }
+
+    #[test]
+    fn rules_graph_example1() {
+        let (rules, _) = parse(EXAMPLE1).unwrap();
+        let graph = rules.iter().copied().collect::<Graph<_>>();
+        let reduced = graph.transitive_reduction().unwrap();
+
+        assert_eq!(vec![97, 75, 47, 61, 53, 29, 13], graph.topological_sort().unwrap());
+        assert_eq!(vec![(29, 13), (47, 61), (53, 29), (61, 53), (75, 47), (97, 75)], reduced.edges().sorted().collect_vec());
+        assert_eq!(7, graph.strongly_connected_components().len());
+
+        let cyclic = graph.subgraph(|page| *page != 13).edges().chain([(29, 97)]).collect::<Graph<_>>();
+        let components = cyclic.strongly_connected_components();
+        assert_eq!(1, components.len());
+        assert_eq!(vec![29, 47, 53, 61, 75, 97], components[0].iter().copied().sorted().collect_vec());
     }
 }
@@ -1,31 +1,223 @@
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+
 use anyhow::{Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 
+/// The value the left operand must have had for `a op b` to produce a given result.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum Operator {
-    Add,
-    Multiply,
-    Concatenate,
-}
-
-impl Operator {
-    fn apply(self, a: u64, b: u64) -> u64 {
-        match self {
-            Operator::Add => a + b,
-            Operator::Multiply => a * b,
-            Operator::Concatenate => {
-                let mut shifted = a;
-                let mut unshifted = b;
-
-                while unshifted > 0 {
-                    shifted *= 10;
-                    unshifted /= 10;
-                }
-
-                shifted + b
-            }
+enum Inverse {
+    Unique(u64),
+    Impossible,
+    Unknown,
+}
+
+/// A binary operator, evaluated strictly left to right. Operators that can be undone from the
+/// result let the solver work backwards from the test value; the rest are searched forwards.
+trait Operator {
+    fn symbol(&self) -> &str;
+
+    /// Returns `None` when the result is undefined or does not fit in a `u64`.
+    fn apply(&self, a: u64, b: u64) -> Option<u64>;
+
+    fn invert(&self, _result: u64, _b: u64) -> Inverse {
+        Inverse::Unknown
+    }
+
+    /// Whether `a op b >= a` always holds, which lets a forward search stop once it overshoots.
+    fn never_decreases(&self) -> bool {
+        false
+    }
+}
+
+/// The power of ten that shifts a number left by as many digits as `value` has, with zero having
+/// no digits, or `None` if that power does not fit in a `u64`.
+fn digits_multiplier(value: u64) -> Option<u64> {
+    let mut multiplier: u64 = 1;
+
+    while multiplier <= value {
+        multiplier = multiplier.checked_mul(10)?;
+    }
+
+    Some(multiplier)
+}
+
+/// The whole number whose `exponent`th power is `value`, if there is one.
+#[allow(dead_code)]
+fn exact_root(value: u64, exponent: u32) -> Option<u64> {
+    let (mut low, mut high) = (0, value);
+
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+
+        match middle.checked_pow(exponent) {
+            Some(power) if power <= value => low = middle,
+            _ => high = middle - 1,
         }
     }
+
+    (low.checked_pow(exponent) == Some(value)).then_some(low)
+}
+
+struct Addition;
+
+impl Operator for Addition {
+    fn symbol(&self) -> &str { "+" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_add(b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        result.checked_sub(b).map_or(Inverse::Impossible, Inverse::Unique)
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
+
+struct Multiplication;
+
+impl Operator for Multiplication {
+    fn symbol(&self) -> &str { "*" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        match b {
+            0 => Inverse::Unknown,
+            _ if result.is_multiple_of(b) => Inverse::Unique(result / b),
+            _ => Inverse::Impossible,
+        }
+    }
+}
+
+struct Concatenation;
+
+impl Operator for Concatenation {
+    fn symbol(&self) -> &str { "||" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(digits_multiplier(b)?)?.checked_add(b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        match digits_multiplier(b) {
+            Some(multiplier) if result % multiplier == b => Inverse::Unique(result / multiplier),
+            _ => Inverse::Impossible,
+        }
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
+
+#[allow(dead_code)]
+struct Subtraction;
+
+impl Operator for Subtraction {
+    fn symbol(&self) -> &str { "-" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_sub(b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        result.checked_add(b).map_or(Inverse::Impossible, Inverse::Unique)
+    }
+}
+
+#[allow(dead_code)]
+struct Exponentiation;
+
+impl Operator for Exponentiation {
+    fn symbol(&self) -> &str { "^" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_pow(b.try_into().ok()?)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        if b == 0 {
+            return Inverse::Unknown;
+        }
+
+        let Ok(exponent) = u32::try_from(b) else { return Inverse::Impossible };
+
+        exact_root(result, exponent).map_or(Inverse::Impossible, Inverse::Unique)
+    }
+}
+
+#[allow(dead_code)]
+struct BitwiseXor;
+
+impl Operator for BitwiseXor {
+    fn symbol(&self) -> &str { "^^" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        Some(a ^ b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        Inverse::Unique(result ^ b)
+    }
+}
+
+#[allow(dead_code)]
+struct BitwiseOr;
+
+impl Operator for BitwiseOr {
+    fn symbol(&self) -> &str { "|" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        Some(a | b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        if result & b == b { Inverse::Unknown } else { Inverse::Impossible }
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+}
+
+#[allow(dead_code)]
+struct BitwiseAnd;
+
+impl Operator for BitwiseAnd {
+    fn symbol(&self) -> &str { "&" }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        Some(a & b)
+    }
+
+    fn invert(&self, result: u64, b: u64) -> Inverse {
+        if result & !b == 0 { Inverse::Unknown } else { Inverse::Impossible }
+    }
+}
+
+/// Operands combined left to right by the operators between them.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Expression {
+    operands: Vec<u64>,
+    operators: Vec<String>,
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.operands[0])?;
+
+        for (operator, operand) in zip(&self.operators, &self.operands[1..]) {
+            write!(f, " {operator} {operand}")?;
+        }
+
+        Ok(())
+    }
 }
 
 type Input = Vec<(u64, Vec<u64>)>;
@@ -51,42 +243,93 @@ fn parse(input: &str) -> Result<Input> {
         .collect()
 }
 
-fn has_valid_solution(test_value: u64, result: u64, operands: &[u64], operators: &[Operator]) -> bool {
-    if operands.is_empty() { return result == test_value; }
-    if result > test_value { return false; }
+/// Peels operands off the end while the operators can be undone, pruning every branch whose
+/// intermediate target is impossible. Operators that cannot be undone fall back to evaluating the
+/// remaining prefix forwards.
+fn solve_backward<'a>(target: u64, operands: &[u64], operators: &[&'a dyn Operator]) -> Option<Vec<&'a dyn Operator>> {
+    let (&last, prefix) = operands.split_last()?;
+
+    if prefix.is_empty() {
+        return (last == target).then(Vec::new);
+    }
+
+    for &operator in operators {
+        let found = match operator.invert(target, last) {
+            Inverse::Unique(value) => solve_backward(value, prefix, operators),
+            Inverse::Impossible => None,
+            Inverse::Unknown => {
+                let bound = if operator.never_decreases() { target } else { u64::MAX };
+                let accept = |value| operator.apply(value, last) == Some(target);
+
+                solve_forward(prefix[0], &prefix[1..], operators, &mut Vec::new(), &accept, bound)
+            },
+        };
+
+        if let Some(mut chosen) = found {
+            chosen.push(operator);
+            return Some(chosen);
+        }
+    }
+
+    None
+}
+
+fn solve_forward<'a>(
+    value: u64,
+    operands: &[u64],
+    operators: &[&'a dyn Operator],
+    chosen: &mut Vec<&'a dyn Operator>,
+    accept: &dyn Fn(u64) -> bool,
+    bound: u64,
+) -> Option<Vec<&'a dyn Operator>> {
+    let Some((&operand, rest)) = operands.split_first() else {
+        return accept(value).then(|| chosen.clone());
+    };
+
+    if value > bound && operators.iter().all(|operator| operator.never_decreases()) {
+        return None;
+    }
 
-    let operand = operands[0];
+    for &operator in operators {
+        let Some(next) = operator.apply(value, operand) else { continue };
 
-    for operator in operators {
-        if has_valid_solution(test_value, operator.apply(result, operand), &operands[1..], operators) {
-            return true;
+        chosen.push(operator);
+        let found = solve_forward(next, rest, operators, chosen, accept, bound);
+        chosen.pop();
+
+        if found.is_some() {
+            return found;
         }
     }
 
-    false
+    None
+}
+
+fn find_expression(test_value: u64, operands: &[u64], operators: &[&dyn Operator]) -> Option<Expression> {
+    let chosen = solve_backward(test_value, operands, operators)?;
+
+    Some(Expression {
+        operands: operands.to_vec(),
+        operators: chosen.iter().map(|operator| operator.symbol().to_string()).collect(),
+    })
 }
 
-fn solve(input: &Input, operators: &[Operator]) -> u64 {
+fn solve(input: &Input, operators: &[&dyn Operator]) -> u64 {
     input
         .iter()
-        .filter_map(|(test_value, operands)| {
-            if has_valid_solution(*test_value, *operands.first()?, &operands[1..], operators) {
-                return Some(test_value);
-            }
-
-            None
-        })
+        .filter(|(test_value, operands)| find_expression(*test_value, operands, operators).is_some())
+        .map(|(test_value, _)| test_value)
         .sum()
 }
 
 #[aoc(day7, part1)]
 fn part1(input: &Input) -> u64 {
-    solve(input, &[Operator::Add, Operator::Multiply])
+    solve(input, &[&Addition, &Multiplication])
 }
 
 #[aoc(day7, part2)]
 fn part2(input: &Input) -> u64 {
-    solve(input, &[Operator::Add, Operator::Multiply, Operator::Concatenate])
+    solve(input, &[&Addition, &Multiplication, &Concatenation])
 }
 
 #[cfg(test)]
@@ -112,6 +355,64 @@ mod tests {
         assert_eq!(3749, part1(&parse(EXAMPLE1).unwrap()));
     }
 
+    #[test]
+    fn find_expression_example1() {
+        let operators: [&dyn Operator; 3] = [&Addition, &Multiplication, &Concatenation];
+
+        assert_eq!("81 * 40 + 27", find_expression(3267, &[81, 40, 27], &operators[..2]).unwrap().to_string());
+        assert_eq!("6 * 8 || 6 * 15", find_expression(7290, &[6, 8, 6, 15], &operators).unwrap().to_string());
+        assert_eq!("15 || 6", find_expression(156, &[15, 6], &operators).unwrap().to_string());
+        assert_eq!(None, find_expression(7290, &[6, 8, 6, 15], &operators[..2]));
+        assert_eq!(None, find_expression(83, &[17, 5], &operators));
+    }
+
+    #[test]
+    fn find_expression_custom_operators() {
+        let operators: [&dyn Operator; 3] = [&Addition, &Subtraction, &Exponentiation];
+
+        assert_eq!("10 - 7 ^ 3", find_expression(27, &[10, 7, 3], &operators).unwrap().to_string());
+        assert_eq!("2 ^ 10 + 0", find_expression(1024, &[2, 10, 0], &operators).unwrap().to_string());
+        assert_eq!("5 ^ 0 + 1", find_expression(2, &[5, 0, 1], &operators).unwrap().to_string());
+        assert_eq!(None, find_expression(28, &[10, 7, 3], &operators));
+    }
+
+    #[test]
+    fn find_expression_bitwise_operators() {
+        let operators: [&dyn Operator; 3] = [&BitwiseAnd, &BitwiseOr, &BitwiseXor];
+
+        assert_eq!("12 & 10 | 1", find_expression(9, &[12, 10, 1], &operators).unwrap().to_string());
+        assert_eq!("12 | 10 & 6", find_expression(6, &[12, 10, 6], &operators).unwrap().to_string());
+        assert_eq!("12 ^^ 10 ^^ 6", find_expression(0, &[12, 10, 6], &operators[2..]).unwrap().to_string());
+        assert_eq!(None, find_expression(16, &[12, 10, 1], &operators));
+    }
+
+    #[test]
+    fn find_expression_overflow() {
+        let operators: [&dyn Operator; 2] = [&Multiplication, &Concatenation];
+
+        assert_eq!(None, find_expression(u64::MAX, &[u64::MAX, 2, 3], &operators));
+        assert_eq!(None, Concatenation.apply(1, 10000000000000000000));
+        assert_eq!(Inverse::Impossible, Concatenation.invert(10000000000000000000, 10000000000000000000));
+    }
+
+    #[test]
+    fn concatenation_with_zero() {
+        assert_eq!(Some(12), Concatenation.apply(12, 0));
+        assert_eq!(Inverse::Unique(12), Concatenation.invert(12, 0));
+        assert_eq!("12 || 0", find_expression(12, &[12, 0], &[&Concatenation]).unwrap().to_string());
+        assert_eq!(Some(1210), Concatenation.apply(12, 10));
+    }
+
+    #[test]
+    fn exponentiation_large_roots() {
+        assert_eq!(Inverse::Unique(4294967295), Exponentiation.invert(18446744065119617025, 2));
+        assert_eq!(Inverse::Impossible, Exponentiation.invert(18446744065119617024, 2));
+        assert_eq!(Inverse::Unique(3), Exponentiation.invert(12157665459056928801, 40));
+        assert_eq!(Inverse::Unique(u64::MAX), Exponentiation.invert(u64::MAX, 1));
+        assert_eq!(Inverse::Impossible, Exponentiation.invert(u64::MAX, 2));
+        assert_eq!(Inverse::Unique(1), Exponentiation.invert(1, 64));
+    }
+
     #[test]
     fn part1_input() {
         assert_eq!(1298300076754, part1(&parse(include_str!("../input/2024/day7.txt")).unwrap()));
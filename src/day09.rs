@@ -1,4 +1,6 @@
-use std::iter::repeat_n;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::iter::from_fn;
 
 use anyhow::{Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
@@ -19,24 +21,123 @@ impl DiskObject {
     }
 }
 
-fn generate_disk_map(input: &[DiskObject]) -> Vec<Option<usize>> {
-    input
-        .iter()
-        .flat_map(|disk_object| {
-            match *disk_object {
-                DiskObject::File(len, id) => repeat_n(Some(id), len),
-                DiskObject::Free(len) => repeat_n(None, len),
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Policy {
+    /// Moves single blocks from the end of the disk into the leftmost free block.
+    Blocks,
+    /// Moves whole files, highest id first, into the leftmost free span that fits them.
+    Files,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct FileSpan {
+    id: usize,
+    start: usize,
+    len: usize,
+}
+
+fn spans(input: &[DiskObject]) -> (Vec<FileSpan>, Vec<(usize, usize)>) {
+    let mut files = Vec::new();
+    let mut frees = Vec::new();
+    let mut start = 0;
+
+    for disk_object in input {
+        match *disk_object {
+            DiskObject::File(len, id) => files.push(FileSpan { id, start, len }),
+            DiskObject::Free(len) => frees.push((start, len)),
+        }
+
+        start += disk_object.len();
+    }
+
+    files.retain(|file| file.len > 0);
+    frees.retain(|&(_, len)| len > 0);
+
+    (files, frees)
+}
+
+/// Moves blocks from the last file into the leftmost free span, yielding each moved piece as soon
+/// as it is placed and the files that stayed put once no free span is left to the left of them.
+fn compact_blocks(mut files: Vec<FileSpan>, frees: Vec<(usize, usize)>) -> impl Iterator<Item = FileSpan> {
+    let mut frees = frees.into_iter().peekable();
+
+    from_fn(move || match (files.last_mut(), frees.peek().copied()) {
+        (Some(file), Some((free_start, free_len))) if free_start < file.start => {
+            let len = file.len.min(free_len);
+            let moved = FileSpan { id: file.id, start: free_start, len };
+            file.len -= len;
+
+            if len == free_len {
+                frees.next();
+            } else {
+                *frees.peek_mut().unwrap() = (free_start + len, free_len - len);
             }
-        })
-        .collect()
+
+            if file.len == 0 {
+                files.pop();
+            }
+
+            Some(moved)
+        },
+        _ => files.pop(),
+    })
 }
 
-fn calculate_disk_checksum(disk_map: &[Option<usize>]) -> usize {
-    disk_map
-        .iter()
-        .enumerate()
-        .filter_map(|(i, b)| b.map(|f| i * f))
-        .sum()
+/// Places the files highest id first, yielding each one at its final position.
+fn compact_files(files: Vec<FileSpan>, frees: Vec<(usize, usize)>) -> impl Iterator<Item = FileSpan> {
+    let max_len = frees.iter().map(|&(_, len)| len).max().unwrap_or(0);
+    let mut frees_by_len = vec![BinaryHeap::new(); max_len + 1];
+
+    for (start, len) in frees {
+        frees_by_len[len].push(Reverse(start));
+    }
+
+    files.into_iter().rev().map(move |mut file| {
+        let leftmost = (file.len..=max_len)
+            .filter_map(|len| frees_by_len[len].peek().map(|&Reverse(start)| (start, len)))
+            .min();
+
+        if let Some((start, len)) = leftmost.filter(|&(start, _)| start < file.start) {
+            frees_by_len[len].pop();
+            frees_by_len[len - file.len].push(Reverse(start + file.len));
+            file.start = start;
+        }
+
+        file
+    })
+}
+
+/// Compacts the disk span by span, in whatever order the policy places them, so the cost depends
+/// on the number of files and not on the size of the disk.
+fn compacted_spans(input: &[DiskObject], policy: Policy) -> Box<dyn Iterator<Item = FileSpan>> {
+    let (files, frees) = spans(input);
+
+    match policy {
+        Policy::Blocks => Box::new(compact_blocks(files, frees)),
+        Policy::Files => Box::new(compact_files(files, frees)),
+    }
+}
+
+/// Compacts the disk and returns where every file, or piece of a file, ends up, ordered by
+/// position.
+fn compact(input: &[DiskObject], policy: Policy) -> Vec<FileSpan> {
+    let mut layout = compacted_spans(input, policy).collect_vec();
+    layout.sort_by_key(|file| file.start);
+    layout
+}
+
+fn span_checksum(file: &FileSpan) -> usize {
+    file.id * (file.start * file.len + file.len * file.len.saturating_sub(1) / 2)
+}
+
+fn layout_checksum(layout: &[FileSpan]) -> usize {
+    layout.iter().map(span_checksum).sum()
+}
+
+/// The checksum of the compacted disk, summed while the spans are placed without collecting or
+/// sorting the layout.
+fn checksum(input: &[DiskObject], policy: Policy) -> usize {
+    compacted_spans(input, policy).map(|file| span_checksum(&file)).sum()
 }
 
 #[aoc_generator(day9)]
@@ -62,83 +163,68 @@ fn parse(input: &str) -> Result<Vec<DiskObject>> {
 
 #[aoc(day9, part1)]
 fn part1(input: &[DiskObject]) -> usize {
-    let mut disk_map = generate_disk_map(input);
-
-    let mut a = 0;
-    let mut b = disk_map.len() - 1;
-
-    while b > a {
-        match (disk_map[a], disk_map[b]) {
-            (Some(_), _) => {
-                a += 1;
-            },
-            (None, Some(_)) => {
-                disk_map.swap(a, b);
-                a += 1;
-                b -= 1;
-            },
-            (None, None) => {
-                b -= 1;
-            },
-        }
-    }
-
-    calculate_disk_checksum(&disk_map)
+    checksum(input, Policy::Blocks)
 }
 
 #[aoc(day9, part2)]
 fn part2(input: &[DiskObject]) -> usize {
-    let mut disk = input.to_vec();
-    let file_ids = disk
-        .iter()
-        .enumerate()
-        .filter_map(|(i, disk_object)| {
-            match *disk_object {
-                DiskObject::File(_, id) => Some((i, id)),
-                DiskObject::Free(_) => None,
-            }
-        })
-        .collect_vec();
+    checksum(input, Policy::Files)
+}
+
+#[aoc(day9, part1, Layout)]
+fn part1_layout(input: &[DiskObject]) -> usize {
+    layout_checksum(&compact(input, Policy::Blocks))
+}
 
-    for &(initial_file_pos, id) in file_ids.iter().rev() {
-        let Some((file_pos, &file)) = disk[initial_file_pos..]
+#[aoc(day9, part2, Layout)]
+fn part2_layout(input: &[DiskObject]) -> usize {
+    layout_checksum(&compact(input, Policy::Files))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::repeat_n;
+
+    use super::*;
+
+    const EXAMPLE1: &str = "2333133121414131402";
+
+    fn generate_disk_map(input: &[DiskObject]) -> Vec<Option<usize>> {
+        input
             .iter()
-            .find_position(|disk_object| {
+            .flat_map(|disk_object| {
                 match *disk_object {
-                    DiskObject::File(_, candidate_id) => *candidate_id == id,
-                    DiskObject::Free(_) => false,
+                    DiskObject::File(len, id) => repeat_n(Some(id), len),
+                    DiskObject::Free(len) => repeat_n(None, len),
                 }
             })
-            .map(|(file_pos, file)| (file_pos + initial_file_pos, file))
-        else {
-            panic!("Unable to find file: {id}");
-        };
-
-        let Some((free_pos, &free)) = disk.iter().take(file_pos).find_position(|disk_object| {
-            match *disk_object {
-                DiskObject::File(_, _) => false,
-                DiskObject::Free(candidate_len) => *candidate_len >= file.len(),
-            }
-        }) else {
-            continue;
-        };
-
-        disk[file_pos] = DiskObject::Free(file.len());
-        disk[free_pos] = file;
+            .collect()
+    }
 
-        if free.len() > file.len() {
-            disk.insert(free_pos + 1, DiskObject::Free(free.len() - file.len()));
-        }
+    fn calculate_disk_checksum(disk_map: &[Option<usize>]) -> usize {
+        disk_map
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| b.map(|f| i * f))
+            .sum()
     }
 
-    calculate_disk_checksum(&generate_disk_map(&disk))
-}
+    /// Expands a layout back into single blocks, which is only practical for small disks.
+    fn layout_disk_map(layout: &[FileSpan]) -> Vec<Option<usize>> {
+        let len = layout.iter().map(|file| file.start + file.len).max().unwrap_or(0);
+        let mut disk_map = vec![None; len];
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        for file in layout {
+            disk_map[file.start..file.start + file.len].fill(Some(file.id));
+        }
 
-    const EXAMPLE1: &str = "2333133121414131402";
+        disk_map
+    }
+
+    fn large_disk(len: usize) -> Vec<DiskObject> {
+        let input = (0..len).map(|i| char::from_digit(((i * 7 + i / 3) % 10) as u32, 10).unwrap()).collect::<String>();
+        parse(&input).unwrap()
+    }
 
     #[test]
     fn generate_disk_map_example1() {
@@ -153,9 +239,65 @@ mod tests {
         assert_eq!("00...111...2...333.44.5555.6666.777.888899", string_rep);
     }
 
+    fn render(disk_map: &[Option<usize>]) -> String {
+        disk_map
+            .iter()
+            .map(|b| match b {
+                Some(f) => f.to_string(),
+                None => ".".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compact_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+
+        assert_eq!("0099811188827773336446555566", render(&layout_disk_map(&compact(&input, Policy::Blocks))));
+        assert_eq!("00992111777.44.333....5555.6666.....8888", render(&layout_disk_map(&compact(&input, Policy::Files))));
+        assert_eq!(
+            vec![FileSpan { id: 0, start: 0, len: 2 }, FileSpan { id: 9, start: 2, len: 2 }, FileSpan { id: 2, start: 4, len: 1 }],
+            compact(&input, Policy::Files)[..3],
+        );
+    }
+
+    #[test]
+    fn layout_checksum_matches_disk_map() {
+        let input = large_disk(4001);
+
+        for policy in [Policy::Blocks, Policy::Files] {
+            let layout = compact(&input, policy);
+            let blocks = input.iter().filter(|o| matches!(o, DiskObject::File(..))).map(DiskObject::len).sum::<usize>();
+
+            assert_eq!(blocks, layout.iter().map(|file| file.len).sum::<usize>());
+            assert_eq!(calculate_disk_checksum(&layout_disk_map(&layout)), layout_checksum(&layout));
+            assert!(layout.iter().tuple_windows().all(|(a, b)| a.start + a.len <= b.start));
+        }
+    }
+
+    #[test]
+    fn checksum_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+
+        assert_eq!(1928, checksum(&input, Policy::Blocks));
+        assert_eq!(2858, checksum(&input, Policy::Files));
+        assert_eq!(0, checksum(&parse("0").unwrap(), Policy::Blocks));
+        assert_eq!(0, checksum(&parse("").unwrap(), Policy::Files));
+    }
+
+    #[test]
+    fn checksum_large_disk() {
+        let input = large_disk(1_000_001);
+
+        for policy in [Policy::Blocks, Policy::Files] {
+            assert_eq!(layout_checksum(&compact(&input, policy)), checksum(&input, policy));
+        }
+    }
+
     #[test]
     fn part1_example1() {
         assert_eq!(1928, part1(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(1928, part1_layout(&parse(EXAMPLE1).unwrap()));
     }
 
     #[test]
@@ -166,10 +308,10 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(2858, part2(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(2858, part2_layout(&parse(EXAMPLE1).unwrap()));
     }
 
     #[test]
-    #[ignore]
     fn part2_input() {
         assert_eq!(6413328569890, part2(&parse(include_str!("../input/2024/day9.txt")).unwrap()));
     }
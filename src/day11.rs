@@ -1,3 +1,6 @@
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
 use hashbrown::HashMap;
 
 use anyhow::{Context, Result};
@@ -23,74 +26,303 @@ fn num_digits(number: u64) -> usize {
     digits
 }
 
-fn solve(stones: &[u64], iterations: usize) -> usize {
-    let mut stone_counts = stones
-        .iter()
-        .fold(HashMap::<u64, usize>::new(), |mut acc, stone| {
-            *acc.entry(*stone).or_default() += 1;
-            acc
-        });
+/// Replaces every stone for which `applies` holds with the stones from `replace`.
+struct Rule {
+    applies: Box<dyn Fn(u64) -> bool>,
+    replace: Box<dyn Fn(u64) -> Vec<u64>>,
+}
 
-    for _ in 0..iterations {
-        let mut new_stone_counts = HashMap::with_capacity(stone_counts.capacity());
+impl Rule {
+    fn new(applies: impl Fn(u64) -> bool + 'static, replace: impl Fn(u64) -> Vec<u64> + 'static) -> Rule {
+        Rule { applies: Box::new(applies), replace: Box::new(replace) }
+    }
+}
 
-        for (stone, count) in stone_counts {
-            if stone == 0 {
-                *new_stone_counts.entry(1).or_default() += count;
-                continue;
+fn standard_rules() -> Vec<Rule> {
+    vec![
+        Rule::new(|stone| stone == 0, |_| vec![1]),
+        Rule::new(|stone| num_digits(stone).is_multiple_of(2), |stone| {
+            let pow = 10u64.pow(num_digits(stone) as u32 / 2);
+            vec![stone / pow, stone % pow]
+        }),
+        Rule::new(|_| true, |stone| vec![stone * 2024]),
+    ]
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct BlinkStats {
+    distinct: usize,
+    total: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Cycle {
+    start: usize,
+    period: usize,
+}
+
+/// What `RuleEngine::run` keeps track of besides the final counts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Tracking {
+    /// Whether to record `BlinkStats` for every iteration.
+    stats: bool,
+    /// How many of the most recent count maps to remember when looking for cycles. Only cycles
+    /// with a period of at most this many iterations are found; `0` turns detection off.
+    cycle_window: usize,
+}
+
+const COUNTS_ONLY: Tracking = Tracking { stats: false, cycle_window: 0 };
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Blinks {
+    counts: HashMap<u64, usize>,
+    /// Statistics for every simulated iteration, starting with the initial stones, if requested.
+    /// Iterations skipped over by a detected cycle are not included.
+    stats: Vec<BlinkStats>,
+    /// The first time the whole count map repeats an earlier one.
+    cycle: Option<Cycle>,
+    /// The first time the set of distinct stones repeats an earlier one, even while the counts
+    /// keep growing.
+    support_cycle: Option<Cycle>,
+}
+
+impl Blinks {
+    fn total(&self) -> Result<usize> {
+        total(&self.counts)
+    }
+}
+
+fn total(counts: &HashMap<u64, usize>) -> Result<usize> {
+    counts
+        .values()
+        .try_fold(0usize, |total, &count| total.checked_add(count))
+        .context("Total stone count overflowed")
+}
+
+/// Hashes a map without depending on its iteration order, so equal maps always hash the same.
+fn unordered_hash<T: Hash>(items: impl Iterator<Item = T>) -> u64 {
+    items
+        .map(|item| {
+            let mut hasher = DefaultHasher::new();
+            item.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+fn same_stones(a: &HashMap<u64, usize>, b: &HashMap<u64, usize>) -> bool {
+    a.len() == b.len() && a.keys().all(|stone| b.contains_key(stone))
+}
+
+/// Applies the first matching rule to every stone, leaving stones that match no rule unchanged.
+/// Each distinct stone is only run through the rules once.
+struct RuleEngine {
+    rules: Vec<Rule>,
+    transitions: HashMap<u64, Vec<u64>>,
+}
+
+impl RuleEngine {
+    fn new(rules: Vec<Rule>) -> RuleEngine {
+        RuleEngine { rules, transitions: HashMap::new() }
+    }
+
+    fn blink(&mut self, stone_counts: &HashMap<u64, usize>) -> Result<HashMap<u64, usize>> {
+        let mut new_stone_counts = HashMap::<u64, usize>::with_capacity(stone_counts.capacity());
+
+        for (&stone, &count) in stone_counts {
+            let rules = &self.rules;
+            let replacements = self.transitions.entry(stone).or_insert_with(|| {
+                rules
+                    .iter()
+                    .find(|rule| (rule.applies)(stone))
+                    .map_or_else(|| vec![stone], |rule| (rule.replace)(stone))
+            });
+
+            for &replacement in replacements.iter() {
+                let new_count = new_stone_counts.entry(replacement).or_default();
+                *new_count = new_count.checked_add(count).context(format!("Count of stone {replacement} overflowed"))?;
             }
+        }
+
+        Ok(new_stone_counts)
+    }
+
+    /// Blinks `iterations` times. Once the count map repeats one within the cycle window, the
+    /// remaining iterations are skipped by jumping ahead a whole number of periods, so huge
+    /// iteration counts are cheap for rules that do not keep growing the pile. Memory stays
+    /// bounded by the window, however long the stones keep changing.
+    fn run(&mut self, stones: &[u64], iterations: usize, tracking: Tracking) -> Result<Blinks> {
+        let stats_of = |counts: &HashMap<u64, usize>| Ok::<_, anyhow::Error>(BlinkStats { distinct: counts.len(), total: total(counts)? });
+
+        let mut stone_counts = HashMap::<u64, usize>::new();
 
-            let digits = num_digits(stone);
-            if digits % 2 == 0 {
-                let pow = 10u64.pow(digits as u32 / 2);
-                let a = stone / pow;
-                let b = stone % pow;
+        for &stone in stones {
+            let count = stone_counts.entry(stone).or_default();
+            *count = count.checked_add(1).context("Stone count overflowed")?;
+        }
+
+        // Recent states with their hashes, where `window[i]` is the state after iteration `first + i`.
+        let mut window = VecDeque::<(u64, u64, HashMap<u64, usize>)>::new();
+        let mut first = 0;
+        let mut seen = HashMap::<u64, usize>::new();
+        let mut seen_supports = HashMap::<u64, usize>::new();
+        let mut stats = Vec::new();
+        let mut cycle = None;
+        let mut support_cycle = None;
 
-                *new_stone_counts.entry(a).or_default() += count;
-                *new_stone_counts.entry(b).or_default() += count;
+        for iteration in 0..=iterations {
+            if iteration > 0 {
+                stone_counts = self.blink(&stone_counts)?;
+            }
+
+            if tracking.stats {
+                stats.push(stats_of(&stone_counts)?);
+            }
 
+            if tracking.cycle_window == 0 {
                 continue;
             }
 
-            *new_stone_counts.entry(stone * 2024).or_default() += count;
+            let state = unordered_hash(stone_counts.iter());
+            let support = unordered_hash(stone_counts.keys());
+            let earlier = |seen: &HashMap<u64, usize>, hash| {
+                let start = *seen.get(&hash)?;
+                Some((start, &window.get(start.checked_sub(first)?)?.2))
+            };
+
+            if support_cycle.is_none() {
+                match earlier(&seen_supports, support) {
+                    Some((start, counts)) if same_stones(counts, &stone_counts) => {
+                        support_cycle = Some(Cycle { start, period: iteration - start });
+                    },
+                    _ => {
+                        seen_supports.insert(support, iteration);
+                    },
+                }
+            }
+
+            if let Some((start, _)) = earlier(&seen, state).filter(|&(_, counts)| *counts == stone_counts) {
+                let period = iteration - start;
+                cycle = Some(Cycle { start, period });
+                stone_counts = window[start + (iterations - start) % period - first].2.clone();
+                break;
+            }
+
+            seen.insert(state, iteration);
+            window.push_back((state, support, stone_counts.clone()));
+
+            if window.len() > tracking.cycle_window {
+                let (state, support, _) = window.pop_front().unwrap();
+
+                if seen.get(&state) == Some(&first) {
+                    seen.remove(&state);
+                }
+
+                if seen_supports.get(&support) == Some(&first) {
+                    seen_supports.remove(&support);
+                }
+
+                first += 1;
+            }
         }
 
-        stone_counts = new_stone_counts;
+        Ok(Blinks { counts: stone_counts, stats, cycle, support_cycle })
     }
+}
 
-    stone_counts
-        .values()
-        .sum()
+fn solve(stones: &[u64], iterations: usize) -> Result<usize> {
+    RuleEngine::new(standard_rules()).run(stones, iterations, COUNTS_ONLY)?.total()
 }
 
 #[aoc(day11, part1)]
-fn part1(stones: &[u64]) -> usize {
+fn part1(stones: &[u64]) -> Result<usize> {
     solve(stones, 25)
 }
 
 #[aoc(day11, part2)]
-fn part2(stones: &[u64]) -> usize {
+fn part2(stones: &[u64]) -> Result<usize> {
     solve(stones, 75)
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     const EXAMPLE1: &str = "125 17";
 
     #[test]
     fn part1_example1() {
-        assert_eq!(55312, part1(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(55312, part1(&parse(EXAMPLE1).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn blink_stats_example1() {
+        let blinks = RuleEngine::new(standard_rules()).run(&parse(EXAMPLE1).unwrap(), 6, Tracking { stats: true, cycle_window: 16 }).unwrap();
+
+        assert_eq!(
+            vec![(2, 2), (3, 3), (4, 4), (5, 5), (8, 9), (12, 13), (15, 22)],
+            blinks.stats.iter().map(|s| (s.distinct, s.total)).collect_vec(),
+        );
+        assert_eq!(22, blinks.total().unwrap());
+        assert_eq!(None, blinks.cycle);
+
+        let counts_only = RuleEngine::new(standard_rules()).run(&parse(EXAMPLE1).unwrap(), 6, COUNTS_ONLY).unwrap();
+        assert_eq!(blinks.counts, counts_only.counts);
+        assert!(counts_only.stats.is_empty());
+    }
+
+    #[test]
+    fn cycle_detection_collatz() {
+        let rules = vec![
+            Rule::new(|stone| stone.is_multiple_of(2), |stone| vec![stone / 2]),
+            Rule::new(|stone| stone != 1, |stone| vec![stone * 3 + 1]),
+            Rule::new(|_| true, |_| vec![4]),
+        ];
+        let mut engine = RuleEngine::new(rules);
+        let blinks = engine.run(&[6, 7], 1_000_000_000_000_000, Tracking { stats: true, cycle_window: 16 }).unwrap();
+
+        assert_eq!(Some(Cycle { start: 14, period: 3 }), blinks.cycle);
+        assert_eq!(Some(Cycle { start: 14, period: 3 }), blinks.support_cycle);
+        assert_eq!(HashMap::<u64, usize>::from_iter([(1, 1), (2, 1)]), blinks.counts);
+        assert_eq!(18, blinks.stats.len());
+
+        let narrow = engine.run(&[6, 7], 1_000_000_000_000_000, Tracking { stats: false, cycle_window: 3 }).unwrap();
+        assert_eq!(Some(Cycle { start: 14, period: 3 }), narrow.cycle);
+        assert_eq!(blinks.counts, narrow.counts);
+
+        let too_narrow = engine.run(&[6, 7], 1000, Tracking { stats: false, cycle_window: 2 }).unwrap();
+        assert_eq!(None, too_narrow.cycle);
+        assert_eq!(blinks.counts, too_narrow.counts);
+    }
+
+    #[test]
+    fn count_overflow() {
+        let rules = vec![Rule::new(|_| true, |stone| vec![stone, stone])];
+        let mut engine = RuleEngine::new(rules);
+
+        assert_eq!(1 << 63, engine.run(&[1], 63, COUNTS_ONLY).unwrap().total().unwrap());
+        assert_eq!("Count of stone 1 overflowed", engine.run(&[1], 64, COUNTS_ONLY).unwrap_err().to_string());
+        assert_eq!("Total stone count overflowed", engine.run(&[1, 2], 63, COUNTS_ONLY).unwrap().total().unwrap_err().to_string());
+    }
+
+    #[test]
+    fn unmatched_stones_stay() {
+        let rules = vec![Rule::new(|stone| stone > 10, |stone| vec![stone / 2, stone - stone / 2])];
+        let blinks = RuleEngine::new(rules).run(&[5, 40], 100, Tracking { stats: false, cycle_window: 16 }).unwrap();
+
+        assert_eq!(HashMap::<u64, usize>::from_iter([(5, 1), (10, 4)]), blinks.counts);
+        assert_eq!(Some(Cycle { start: 2, period: 1 }), blinks.cycle);
     }
 
     #[test]
     fn part1_input() {
-        assert_eq!(204022, part1(&parse(include_str!("../input/2024/day11.txt")).unwrap()));
+        assert_eq!(204022, part1(&parse(include_str!("../input/2024/day11.txt")).unwrap()).unwrap());
     }
 
     #[test]
     fn part2_input() {
-        assert_eq!(241651071960597, part2(&parse(include_str!("../input/2024/day11.txt")).unwrap()));
+        assert_eq!(241651071960597, part2(&parse(include_str!("../input/2024/day11.txt")).unwrap()).unwrap());
     }
 }
@@ -1,63 +1,404 @@
-use anyhow::{Context, Result};
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
+use derive_more::derive::Display;
+use itertools::Itertools;
 use scan_fmt::scan_fmt;
 
-type ClawMachine = ((i64, i64), (i64, i64), (i64, i64));
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Button {
+    name: char,
+    movement: (i64, i64),
+    cost: i64,
+    limit: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct ClawMachine {
+    buttons: Vec<Button>,
+    prize: (i64, i64),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Solution {
+    presses: Vec<i64>,
+    cost: i64,
+}
+
+/// Why no combination of button presses wins the prize.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+enum Unreachable {
+    #[display("Prize is not on the line along which the buttons move the claw")]
+    OffLine,
+    #[display("Prize cannot be reached with a whole number of presses")]
+    NotDivisible,
+    #[display("Reaching the prize needs a negative number of presses of button {_0}")]
+    NegativePresses(char),
+    #[display("Reaching the prize needs {presses} presses of button {button}, more than its limit of {limit}")]
+    OverLimit { button: char, presses: i64, limit: i64 },
+    #[display("Button {_0} has no press limit and could be pressed without bound")]
+    Unbounded(char),
+    #[display("No combination of presses within the limits reaches the prize")]
+    NoCombination,
+    #[display("Button {button} could be pressed up to {bound} times, more than the {MAX_SEARCHED_PRESSES} a search tries")]
+    SearchTooLarge { button: char, bound: i128 },
+}
+
+/// Most press counts tried for each button beyond the three that are solved directly.
+const MAX_SEARCHED_PRESSES: i128 = 10_000;
+
+impl std::error::Error for Unreachable {}
+
 type Input = Vec<ClawMachine>;
 
+/// How the machines of a part are operated: the tokens each button costs, how often each button
+/// may be pressed, and how far the prizes are moved.
+#[derive(Clone, Copy, Debug)]
+struct Setup<'a> {
+    costs: &'a [(char, i64)],
+    limit: Option<i64>,
+    offset: i64,
+}
+
+const PART1: Setup = Setup { costs: &[('A', 3), ('B', 1)], limit: Some(100), offset: 0 };
+const PART2: Setup = Setup { costs: &[('A', 3), ('B', 1)], limit: None, offset: 10000000000000 };
+
+/// Parses the machines with free, unlimited buttons; `configure` applies the `Setup` of a part.
 #[aoc_generator(day13)]
 fn parse(input: &str) -> Result<Input> {
     input
         .split("\n\n")
         .map(|machine| {
-            let mut lines = machine.lines();
-            let (ax, ay) = scan_fmt!(lines.next().context("Unexpected end of input")?, "Button A: X+{d}, Y+{d}", i64, i64)?;
-            let (bx, by) = scan_fmt!(lines.next().context("Unexpected end of input")?, "Button B: X+{d}, Y+{d}", i64, i64)?;
-            let (px, py) = scan_fmt!(lines.next().context("Unexpected end of input")?, "Prize: X={d}, Y={d}", i64, i64)?;
+            let lines = machine.lines().collect_vec();
+            let (prize, buttons) = lines.split_last().context("Unexpected end of input")?;
+
+            let buttons = buttons
+                .iter()
+                .map(|line| {
+                    let (name, x, y) = scan_fmt!(line, "Button {}: X{d}, Y{d}", char, i64, i64)?;
+                    Ok(Button { name, movement: (x, y), cost: 0, limit: None })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let (px, py) = scan_fmt!(prize, "Prize: X={d}, Y={d}", i64, i64)?;
+
+            if buttons.is_empty() {
+                bail!("Claw machine without buttons: {machine}");
+            }
 
-            Ok(((ax, ay), (bx, by), (px, py)))
+            Ok(ClawMachine { buttons, prize: (px, py) })
         })
         .collect()
 }
 
-fn find_cost(machine: ClawMachine) -> Option<i64> {
-    let ((ax, ay), (bx, by), (px, py)) = machine;
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a.abs(), a.signum(), 0);
+    }
+
+    let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+    (g, y, x - a.div_euclid(b) * y)
+}
+
+fn check_presses(button: &Button, presses: i128) -> Result<i64, Unreachable> {
+    if presses < 0 {
+        return Err(Unreachable::NegativePresses(button.name));
+    }
+
+    match button.limit {
+        Some(limit) if presses > limit as i128 => Err(Unreachable::OverLimit { button: button.name, presses: presses as i64, limit }),
+        _ => Ok(presses as i64),
+    }
+}
+
+fn solution(buttons: &[Button], presses: Vec<i64>) -> Solution {
+    let cost = buttons.iter().zip(&presses).map(|(button, presses)| button.cost * presses).sum();
+    Solution { presses, cost }
+}
+
+fn solve_single(button: &Button, (px, py): (i128, i128)) -> Result<Solution, Unreachable> {
+    let (vx, vy) = (button.movement.0 as i128, button.movement.1 as i128);
+
+    let presses = match (vx, vy) {
+        (0, 0) if (px, py) == (0, 0) => 0,
+        (0, 0) => return Err(Unreachable::OffLine),
+        (0, _) => py / vy,
+        _ => px / vx,
+    };
+
+    if (presses * vx, presses * vy) != (px, py) {
+        return Err(if px * vy != py * vx { Unreachable::OffLine } else { Unreachable::NotDivisible });
+    }
+
+    Ok(solution(&[*button], vec![check_presses(button, presses)?]))
+}
+
+/// Two buttons that do not move the claw along the same line have exactly one solution.
+fn solve_independent(buttons: [&Button; 2], (px, py): (i128, i128)) -> Result<Solution, Unreachable> {
+    let [a, b] = buttons;
+    let ((ax, ay), (bx, by)) = ((a.movement.0 as i128, a.movement.1 as i128), (b.movement.0 as i128, b.movement.1 as i128));
 
     let det = ax * by - ay * bx;
-    let a = (by * px - bx * py) / det;
-    let b = (ax * py - ay * px) / det;
+    let (a_numerator, b_numerator) = (by * px - bx * py, ax * py - ay * px);
+
+    if a_numerator % det != 0 || b_numerator % det != 0 {
+        return Err(Unreachable::NotDivisible);
+    }
+
+    let presses = vec![check_presses(a, a_numerator / det)?, check_presses(b, b_numerator / det)?];
+    Ok(solution(&[*a, *b], presses))
+}
+
+/// Two buttons moving along the same line reduce to `sa * na + sb * nb = t` along that line,
+/// whose solutions are found with the extended Euclidean algorithm. The cost is linear along the
+/// family of solutions, so the cheapest one sits at an end of the feasible range.
+fn solve_collinear(buttons: [&Button; 2], (px, py): (i128, i128)) -> Result<Solution, Unreachable> {
+    let [a, b] = buttons;
+
+    if a.movement == (0, 0) && b.movement == (0, 0) {
+        return match (px, py) {
+            (0, 0) => Ok(solution(&[*a, *b], vec![0, 0])),
+            _ => Err(Unreachable::OffLine),
+        };
+    }
 
-    if (ax * a + bx * b, ay * a + by * b) != (px, py) {
+    let (dx, dy) = if a.movement != (0, 0) { a.movement } else { b.movement };
+    let (dx, dy) = (dx as i128, dy as i128);
+    let unit = extended_gcd(dx, dy).0;
+    let (ux, uy) = (dx / unit, dy / unit);
+
+    if px * uy != py * ux {
+        return Err(Unreachable::OffLine);
+    }
+
+    let along = |(x, y): (i128, i128)| if ux != 0 { x / ux } else { y / uy };
+    let (sa, sb, t) = (along((a.movement.0 as i128, a.movement.1 as i128)), along((b.movement.0 as i128, b.movement.1 as i128)), along((px, py)));
+
+    let (g, x, y) = extended_gcd(sa, sb);
+
+    if t % g != 0 {
+        return Err(Unreachable::NotDivisible);
+    }
+
+    // na = base_a + k * step_a, nb = base_b + k * step_b for any integer k.
+    cheapest_along(&[(a, x * (t / g), sb / g), (b, y * (t / g), -sa / g)])
+}
+
+/// Picks the cheapest point of a one-parameter family of solutions, where button `i` is pressed
+/// `base + k * step` times for any integer `k`. The cost is linear in `k`, so the cheapest point
+/// sits at an end of the range of `k` that keeps every press count within its limits.
+fn cheapest_along(terms: &[(&Button, i128, i128)]) -> Result<Solution, Unreachable> {
+    let (mut low, mut high) = (i128::MIN, i128::MAX);
+
+    for &(button, base, step) in terms {
+        let upper = button.limit.map(i128::from);
+
+        match step.cmp(&0) {
+            Ordering::Equal => {
+                check_presses(button, base)?;
+            },
+            Ordering::Greater => {
+                low = low.max((-base).div_euclid(step) + i128::from((-base).rem_euclid(step) != 0));
+                if let Some(upper) = upper { high = high.min((upper - base).div_euclid(step)); }
+            },
+            Ordering::Less => {
+                high = high.min(base.div_euclid(-step));
+                if let Some(upper) = upper { low = low.max((base - upper).div_euclid(-step) + i128::from((base - upper).rem_euclid(-step) != 0)); }
+            },
+        }
+    }
+
+    if low > high {
+        return Err(Unreachable::NoCombination);
+    }
+
+    let slope = terms.iter().map(|(button, _, step)| button.cost as i128 * step).sum::<i128>();
+    let k = match slope.cmp(&0) {
+        Ordering::Greater | Ordering::Equal if low != i128::MIN => low,
+        Ordering::Less | Ordering::Equal if high != i128::MAX => high,
+        _ => {
+            let unbounded = terms.iter().find(|(button, _, step)| *step != 0 && button.limit.is_none()).unwrap();
+            return Err(Unreachable::Unbounded(unbounded.0.name));
+        },
+    };
+
+    let buttons = terms.iter().map(|(button, _, _)| **button).collect_vec();
+    let presses = terms.iter().map(|(_, base, step)| (base + k * step) as i64).collect_vec();
+    Ok(solution(&buttons, presses))
+}
+
+/// Solves `factor * n = value (mod modulus)` as `n = r (mod m)`, if it has any solution.
+fn solve_congruence(factor: i128, value: i128, modulus: i128) -> Option<(i128, i128)> {
+    let (g, inverse, _) = extended_gcd(factor, modulus);
+
+    if value.rem_euclid(g) != 0 {
         return None;
     }
 
-    Some(a * 3 + b)
+    let modulus = modulus / g;
+    Some(((value / g).rem_euclid(modulus) * inverse.rem_euclid(modulus) % modulus, modulus))
+}
+
+/// Combines `n = r1 (mod m1)` and `n = r2 (mod m2)` into a single congruence, if they agree.
+fn combine_congruences((r1, m1): (i128, i128), (r2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (k, m) = solve_congruence(m1, r2 - r1, m2)?;
+    let modulus = m1 * m;
+
+    Some(((r1 + m1 * k).rem_euclid(modulus), modulus))
+}
+
+/// Three buttons where `a` and `b` move the claw in independent directions. Pressing `c` some `n`
+/// times leaves a 2x2 system for `a` and `b`, whose solution is a whole number exactly when `n`
+/// satisfies two congruences. Those combine into `n = r + k * m`, and every press count is then
+/// linear in `k`.
+fn solve_three(buttons: [&Button; 3], (px, py): (i128, i128)) -> Result<Solution, Unreachable> {
+    let [a, b, c] = buttons;
+    let movement = |button: &Button| (button.movement.0 as i128, button.movement.1 as i128);
+    let ((ax, ay), (bx, by), (cx, cy)) = (movement(a), movement(b), movement(c));
+
+    let det = ax * by - ay * bx;
+    let (a_base, a_factor) = (by * px - bx * py, by * cx - bx * cy);
+    let (b_base, b_factor) = (ax * py - ay * px, ax * cy - ay * cx);
+
+    let (r, m) = solve_congruence(a_factor, a_base, det.abs())
+        .zip(solve_congruence(b_factor, b_base, det.abs()))
+        .and_then(|(a_congruence, b_congruence)| combine_congruences(a_congruence, b_congruence))
+        .ok_or(Unreachable::NotDivisible)?;
+
+    cheapest_along(&[
+        (a, (a_base - r * a_factor) / det, -m * a_factor / det),
+        (b, (b_base - r * b_factor) / det, -m * b_factor / det),
+        (c, r, m),
+    ])
+}
+
+fn is_independent(a: &Button, b: &Button) -> bool {
+    a.movement.0 as i128 * b.movement.1 as i128 != a.movement.1 as i128 * b.movement.0 as i128
+}
+
+/// Largest number of presses of `buttons[index]` that could still be part of a solution, given
+/// how far the other buttons can move the claw in each direction. `None` if nothing bounds it.
+fn press_bound(buttons: &[Button], index: usize, prize: (i128, i128)) -> Option<i128> {
+    let button = &buttons[index];
+    let others = buttons.iter().enumerate().filter(|(i, _)| *i != index).map(|(_, other)| other).collect_vec();
+    let component = |button: &Button, axis: usize| if axis == 0 { button.movement.0 as i128 } else { button.movement.1 as i128 };
+
+    // The furthest the other buttons can move the claw along an axis, in the direction of `sign`.
+    let reach = |axis: usize, sign: i128| {
+        others.iter().try_fold(0, |total, other| match component(other, axis) * sign {
+            v if v <= 0 => Some(total),
+            v => Some(total + v * i128::from(other.limit?)),
+        })
+    };
+
+    [(0, prize.0), (1, prize.1)]
+        .into_iter()
+        .filter_map(|(axis, target)| match component(button, axis) {
+            0 => None,
+            v if v > 0 => Some((target + reach(axis, -1)?).div_euclid(v)),
+            v => Some((reach(axis, 1)? - target).div_euclid(-v)),
+        })
+        .chain(button.limit.map(i128::from))
+        .min()
+}
+
+fn solve_buttons(buttons: &[Button], prize: (i128, i128)) -> Result<Solution, Unreachable> {
+    match buttons {
+        [] if prize == (0, 0) => Ok(Solution { presses: vec![], cost: 0 }),
+        [] => Err(Unreachable::NoCombination),
+        [button] => solve_single(button, prize),
+        [a, b] if is_independent(a, b) => solve_independent([a, b], prize),
+        [a, b] => solve_collinear([a, b], prize),
+        [a, b, c] if is_independent(a, b) => solve_three([a, b, c], prize),
+        [a, b, c] if is_independent(a, c) => solve_three([a, c, b], prize).map(|solution| reorder(solution, [0, 2, 1])),
+        [a, b, c] if is_independent(b, c) => solve_three([b, c, a], prize).map(|solution| reorder(solution, [2, 0, 1])),
+        [rest @ .., last] => {
+            let bound = press_bound(buttons, buttons.len() - 1, prize).ok_or(Unreachable::Unbounded(last.name))?;
+
+            if bound > MAX_SEARCHED_PRESSES {
+                return Err(Unreachable::SearchTooLarge { button: last.name, bound });
+            }
+
+            let (vx, vy) = (last.movement.0 as i128, last.movement.1 as i128);
+
+            (0..=bound.max(-1))
+                .filter_map(|presses| {
+                    let mut solution = solve_buttons(rest, (prize.0 - presses * vx, prize.1 - presses * vy)).ok()?;
+                    solution.presses.push(presses as i64);
+                    solution.cost += last.cost * presses as i64;
+                    Some(solution)
+                })
+                .min_by_key(|solution| solution.cost)
+                .ok_or(Unreachable::NoCombination)
+        },
+    }
+}
+
+/// Puts presses found for a permutation of the buttons back in their original order, where
+/// `order[i]` is the original position of the `i`th permuted button.
+fn reorder(solution: Solution, order: [usize; 3]) -> Solution {
+    let mut presses = vec![0; 3];
+
+    for (presses_of_button, &position) in solution.presses.into_iter().zip(&order) {
+        presses[position] = presses_of_button;
+    }
+
+    Solution { presses, cost: solution.cost }
+}
+
+/// Finds the cheapest way to win the prize. Up to three buttons are solved directly, as long as
+/// two of them move the claw in independent directions. Any further button is tried with every
+/// press count that the reach of the other buttons allows, up to `MAX_SEARCHED_PRESSES`.
+fn find_cost(machine: &ClawMachine) -> Result<Solution, Unreachable> {
+    solve_buttons(&machine.buttons, (machine.prize.0 as i128, machine.prize.1 as i128))
 }
 
-fn find_costs(machines: impl IntoIterator<Item = ClawMachine>) -> impl IntoIterator<Item = Option<i64>> {
+fn find_costs<'a>(machines: impl IntoIterator<Item = &'a ClawMachine>) -> impl IntoIterator<Item = Result<Solution, Unreachable>> {
     machines
         .into_iter()
         .map(find_cost)
 }
 
-fn shift_targets(machines: &Input, offset: i64) -> impl IntoIterator<Item = ClawMachine> + use<'_> {
+/// Applies the costs, press limit and prize offset of `setup` to every machine.
+fn configure(machines: &Input, setup: &Setup) -> Result<Input> {
     machines
         .iter()
-        .map(move |&(a, b, (px, py))| (a, b, (px + offset, py + offset)))
+        .map(|machine| {
+            let buttons = machine
+                .buttons
+                .iter()
+                .map(|&button| {
+                    let (_, cost) = setup
+                        .costs
+                        .iter()
+                        .find(|(name, _)| *name == button.name)
+                        .ok_or_else(|| anyhow!("No cost configured for button {}", button.name))?;
+
+                    Ok(Button { cost: *cost, limit: setup.limit, ..button })
+                })
+                .collect::<Result<_>>()?;
+
+            Ok(ClawMachine { buttons, prize: (machine.prize.0 + setup.offset, machine.prize.1 + setup.offset) })
+        })
+        .collect()
 }
 
-fn solve(machines: &Input, multiplier: i64) -> i64 {
-    find_costs(shift_targets(machines, multiplier)).into_iter().flatten().sum()
+fn solve(machines: &Input, setup: &Setup) -> Result<i64> {
+    Ok(find_costs(&configure(machines, setup)?)
+        .into_iter()
+        .flatten()
+        .map(|solution| solution.cost)
+        .sum())
 }
 
 #[aoc(day13, part1)]
-fn part1(input: &Input) -> i64 {
-    solve(input, 0)
+fn part1(input: &Input) -> Result<i64> {
+    solve(input, &PART1)
 }
 
 #[aoc(day13, part2)]
-fn part2(input: &Input) -> i64 {
-    solve(input, 10000000000000)
+fn part2(input: &Input) -> Result<i64> {
+    solve(input, &PART2)
 }
 
 #[cfg(test)]
@@ -87,12 +428,107 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(480, part1(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(480, part1(&parse(EXAMPLE1).unwrap()).unwrap());
+    }
+
+    fn machine(buttons: &[((i64, i64), i64, Option<i64>)], prize: (i64, i64)) -> ClawMachine {
+        let buttons = buttons
+            .iter()
+            .zip('A'..)
+            .map(|(&(movement, cost, limit), name)| Button { name, movement, cost, limit })
+            .collect();
+
+        ClawMachine { buttons, prize }
+    }
+
+    #[test]
+    fn find_cost_example1() {
+        let machines = configure(&parse(EXAMPLE1).unwrap(), &PART1).unwrap();
+
+        assert_eq!(Ok(Solution { presses: vec![80, 40], cost: 280 }), find_cost(&machines[0]));
+        assert_eq!(Err(Unreachable::NotDivisible), find_cost(&machines[1]));
+        assert_eq!(Ok(Solution { presses: vec![38, 86], cost: 200 }), find_cost(&machines[2]));
+
+        let limited = configure(&machines, &Setup { limit: Some(50), ..PART1 }).unwrap();
+        let error = find_cost(&limited[0]).unwrap_err();
+        assert_eq!(Unreachable::OverLimit { button: 'A', presses: 80, limit: 50 }, error);
+        assert_eq!("Reaching the prize needs 80 presses of button A, more than its limit of 50", error.to_string());
+    }
+
+    #[test]
+    fn find_cost_collinear() {
+        assert_eq!(Ok(Solution { presses: vec![0, 10], cost: 10 }), find_cost(&machine(&[((2, 2), 3, None), ((1, 1), 1, None)], (10, 10))));
+        assert_eq!(Ok(Solution { presses: vec![3, 4], cost: 13 }), find_cost(&machine(&[((2, 2), 3, None), ((1, 1), 1, Some(4))], (10, 10))));
+        assert_eq!(Ok(Solution { presses: vec![3, 1], cost: 4 }), find_cost(&machine(&[((3, 3), 1, None), ((1, 1), 1, None)], (10, 10))));
+        assert_eq!(Ok(Solution { presses: vec![2, 1], cost: 7 }), find_cost(&machine(&[((2, 4), 3, None), ((-1, -2), 1, None)], (3, 6))));
+        assert_eq!(Err(Unreachable::OffLine), find_cost(&machine(&[((3, 3), 1, None), ((1, 1), 1, None)], (10, 11))));
+        assert_eq!(Err(Unreachable::NotDivisible), find_cost(&machine(&[((2, 2), 1, None), ((4, 4), 1, None)], (5, 5))));
+        assert_eq!(Err(Unreachable::NoCombination), find_cost(&machine(&[((2, 2), 1, Some(1)), ((4, 4), 1, Some(1))], (8, 8))));
+        assert_eq!(Ok(Solution { presses: vec![0, 0], cost: 0 }), find_cost(&machine(&[((0, 0), 3, None), ((0, 0), 1, None)], (0, 0))));
+        assert_eq!(Err(Unreachable::OffLine), find_cost(&machine(&[((0, 0), 3, None), ((0, 0), 1, None)], (1, 0))));
+    }
+
+    #[test]
+    fn find_cost_unreachable() {
+        assert_eq!(Err(Unreachable::NegativePresses('A')), find_cost(&machine(&[((1, 0), 3, None), ((0, 1), 1, None)], (-1, 2))));
+        assert_eq!(Err(Unreachable::OffLine), find_cost(&machine(&[((1, 2), 3, None)], (2, 3))));
+        assert_eq!(Err(Unreachable::NotDivisible), find_cost(&machine(&[((2, 4), 3, None)], (3, 6))));
+        assert_eq!(Err(Unreachable::NoCombination), find_cost(&machine(&[((1, 0), 3, None), ((0, 1), 1, None), ((1, -1), 1, None)], (-1, 3))));
+        assert_eq!(Err(Unreachable::NotDivisible), find_cost(&machine(&[((2, 0), 3, None), ((0, 2), 1, None), ((2, 2), 1, None)], (3, 3))));
+        assert_eq!(Err(Unreachable::Unbounded('D')), find_cost(&machine(&[((1, 0), 3, None), ((0, 1), 1, None), ((1, -1), 1, None), ((0, -1), 1, None)], (2, 3))));
+    }
+
+    #[test]
+    fn find_cost_three_buttons() {
+        let machines = parse(indoc! {"
+            Button A: X+3, Y+0
+            Button B: X+0, Y+2
+            Button C: X+1, Y+1
+            Prize: X=4, Y=4
+
+            Button A: X+3, Y+0
+            Button B: X+0, Y+2
+            Button C: X+1, Y+1
+            Prize: X=6, Y=4
+        "}).unwrap();
+        let setup = Setup { costs: &[('A', 3), ('B', 1), ('C', 1)], limit: None, offset: 0 };
+        let machines = configure(&machines, &setup).unwrap();
+
+        assert_eq!(Ok(Solution { presses: vec![0, 0, 4], cost: 4 }), find_cost(&machines[0]));
+        assert_eq!(Ok(Solution { presses: vec![2, 2, 0], cost: 8 }), find_cost(&machines[1]));
+        assert_eq!(Err(Unreachable::NoCombination), find_cost(&configure(&machines, &Setup { limit: Some(2), ..setup }).unwrap()[0]));
+
+        let error = configure(&machines, &PART1).unwrap_err();
+        assert_eq!("No cost configured for button C", error.to_string());
+    }
+
+    #[test]
+    fn find_cost_mixed_signs() {
+        assert_eq!(Ok(Solution { presses: vec![0, 5, 2], cost: 7 }), find_cost(&machine(&[((1, 0), 3, None), ((0, 1), 1, None), ((1, -1), 1, None)], (2, 3))));
+        assert_eq!(Ok(Solution { presses: vec![1, 1, 1], cost: 5 }), find_cost(&machine(&[((-1, 0), 3, None), ((0, 1), 1, None), ((2, 1), 1, None)], (1, 2))));
+        assert_eq!(Ok(Solution { presses: vec![3, 0, 2], cost: 5 }), find_cost(&machine(&[((-1, 0), 1, None), ((0, 1), 5, None), ((2, 1), 1, None)], (1, 2))));
+        assert_eq!(Ok(Solution { presses: vec![0, 1, 3, 1], cost: 6 }), find_cost(&machine(&[((1, 0), 4, Some(5)), ((0, 1), 2, Some(5)), ((1, -1), 1, Some(5)), ((-1, 3), 1, Some(5))], (2, 1))));
+    }
+
+    #[test]
+    fn find_cost_four_buttons_far_prize() {
+        let buttons = [((1, 0), 3, None), ((0, 1), 1, None), ((1, -1), 1, None), ((1, 1), 1, None)];
+        let error = find_cost(&machine(&buttons, (10000000000004, 10000000000004))).unwrap_err();
+
+        assert_eq!(Unreachable::SearchTooLarge { button: 'D', bound: 10000000000004 }, error);
+        assert_eq!("Button D could be pressed up to 10000000000004 times, more than the 10000 a search tries", error.to_string());
+        assert_eq!(Ok(Solution { presses: vec![0, 0, 0, 4], cost: 4 }), find_cost(&machine(&buttons, (4, 4))));
+    }
+
+    #[test]
+    fn find_cost_three_buttons_far_prize() {
+        let machine = machine(&[((3, 0), 3, None), ((0, 2), 1, None), ((1, 1), 1, None)], (10000000000004, 10000000000004));
+        assert_eq!(Ok(Solution { presses: vec![0, 0, 10000000000004], cost: 10000000000004 }), find_cost(&machine));
     }
 
     #[test]
     fn part1_input() {
-        let ans = part1(&parse(include_str!("../input/2024/day13.txt")).unwrap());
+        let ans = part1(&parse(include_str!("../input/2024/day13.txt")).unwrap()).unwrap();
         assert!(56126 > ans);
         assert!(34773 < ans);
         assert!(47793 > ans);
@@ -102,15 +538,15 @@ mod tests {
     #[test]
     fn part2_example1() {
         let machines = parse(EXAMPLE1).unwrap();
-        let has_solutions = find_costs(shift_targets(&machines, 10000000000000))
+        let has_solutions = find_costs(&configure(&machines, &PART2).unwrap())
             .into_iter()
-            .map(|solution| solution.is_some())
+            .map(|solution| solution.is_ok())
             .collect_vec();
         assert_eq!(vec![false, true, false, true], has_solutions);
     }
 
     #[test]
     fn part2_input() {
-        assert_eq!(79352015273424, part2(&parse(include_str!("../input/2024/day13.txt")).unwrap()));
+        assert_eq!(79352015273424, part2(&parse(include_str!("../input/2024/day13.txt")).unwrap()).unwrap());
     }
 }
@@ -1,10 +1,18 @@
 use std::cmp::max;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
 use anyhow::Result;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use scan_fmt::scan_fmt;
 
+use crate::utils::frame_score::{top_k, FrameScorer, LargestCluster};
+use crate::utils::grid::Grid;
+
 type Input = Vec<((isize, isize), (isize, isize))>;
 type NormalizedInput = Vec<((usize, usize), (usize, usize))>;
 
@@ -19,6 +27,10 @@ fn parse(input: &str) -> Result<Input> {
         .collect()
 }
 
+/// Room size of the puzzle input as `(height, width)`. The examples use a smaller room, so the
+/// `_with_dimensions` functions take it explicitly.
+const ROOM: (usize, usize) = (103, 101);
+
 fn normalize(input: &Input, (height, width): (usize, usize)) -> NormalizedInput {
     input
         .iter()
@@ -52,7 +64,7 @@ fn part1_with_dimensions(input: &Input, (height, width): (usize, usize)) -> Opti
 
 #[aoc(day14, part1)]
 fn part1(input: &Input) -> Option<usize> {
-    part1_with_dimensions(input, (103, 101))
+    part1_with_dimensions(input, ROOM)
 }
 
 fn variance(values: &[usize]) -> usize {
@@ -60,9 +72,25 @@ fn variance(values: &[usize]) -> usize {
     values.iter().map(|value| value.abs_diff(mean).pow(2)).sum::<usize>() / values.len()
 }
 
-#[aoc(day14, part2)]
-fn part2(input: &Input) -> Option<usize> {
-    let (height, width) = (103, 101);
+fn frame(input: &NormalizedInput, (height, width): (usize, usize), iterations: usize) -> Grid<usize> {
+    let mut frame = Grid::new(height, width);
+
+    for (i, j) in simulate(input, (height, width), iterations) {
+        frame[(i, j)] += 1;
+    }
+
+    frame
+}
+
+/// Scores every second until the robots repeat and returns the `k` most structured ones.
+fn candidate_frames(input: &Input, (height, width): (usize, usize), scorer: &impl FrameScorer, k: usize) -> Vec<(usize, f64)> {
+    let input = normalize(input, (height, width));
+    let frames = (0..height * width).map(|iterations| (iterations, frame(&input, (height, width), iterations)));
+
+    top_k(frames, scorer, k)
+}
+
+fn part2_with_dimensions(input: &Input, (height, width): (usize, usize)) -> Option<usize> {
     let input = normalize(input, (height, width));
 
     let (variances_i, variances_j): (Vec<_>, Vec<_>) = (0..max(height, width))
@@ -78,9 +106,108 @@ fn part2(input: &Input) -> Option<usize> {
         .find(|&iterations| iterations.abs_diff(offset_i) % height == 0 && iterations.abs_diff(offset_j) % width == 0)
 }
 
+#[aoc(day14, part2)]
+fn part2(input: &Input) -> Option<usize> {
+    part2_with_dimensions(input, ROOM)
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FrameFormat {
+    Text,
+    Pbm,
+    Png { scale: usize },
+}
+
+#[allow(dead_code)]
+impl FrameFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Text => "txt",
+            FrameFormat::Pbm => "pbm",
+            FrameFormat::Png { .. } => "png",
+        }
+    }
+}
+
+/// Draws a frame the way the puzzle does: robot counts, with `.` for empty tiles.
+fn render_text(frame: &Grid<usize>) -> String {
+    frame.render(|_, &count| match count {
+        0 => '.',
+        1..=9 => char::from_digit(count as u32, 10).unwrap(),
+        _ => '+',
+    })
+}
+
+fn render_frame(frame: &Grid<usize>, format: FrameFormat) -> Result<Vec<u8>> {
+    match format {
+        FrameFormat::Text => Ok(render_text(frame).into_bytes()),
+        FrameFormat::Pbm => Ok(frame.render_pbm(|_, &count| count > 0).into_bytes()),
+        FrameFormat::Png { scale } => frame.render_png(scale, |_, &count| if count > 0 { [40, 160, 40] } else { [0, 0, 0] }),
+    }
+}
+
+/// Renders the room after each of the given seconds, for example a range or the seconds returned
+/// by `candidate_frames`.
+fn dump_frames(input: &Input, (height, width): (usize, usize), seconds: impl IntoIterator<Item = usize>, format: FrameFormat) -> Result<Vec<(usize, Vec<u8>)>> {
+    let input = normalize(input, (height, width));
+
+    seconds
+        .into_iter()
+        .map(|iterations| Ok((iterations, render_frame(&frame(&input, (height, width), iterations), format)?)))
+        .collect()
+}
+
+#[allow(dead_code)]
+fn save_frames(directory: &Path, frames: &[(usize, Vec<u8>)], format: FrameFormat) -> Result<()> {
+    fs::create_dir_all(directory)?;
+
+    for (iterations, data) in frames {
+        fs::write(directory.join(format!("second-{iterations:05}.{}", format.extension())), data)?;
+    }
+
+    Ok(())
+}
+
+/// Plays the given seconds in a terminal, redrawing the room in place every `delay`.
+#[allow(dead_code)]
+fn animate(input: &Input, (height, width): (usize, usize), seconds: impl IntoIterator<Item = usize>, output: &mut impl Write, delay: Duration) -> Result<()> {
+    let input = normalize(input, (height, width));
+
+    for iterations in seconds {
+        write!(output, "\x1b[H\x1b[2JSecond {iterations}\n{}", render_text(&frame(&input, (height, width), iterations)))?;
+        output.flush()?;
+        thread::sleep(delay);
+    }
+
+    Ok(())
+}
+
+/// Lists the most structured seconds with their scores and draws the best one, so the part 2
+/// answer can be confirmed by eye.
+#[allow(dead_code)]
+fn part2_candidates(input: &Input) -> Result<String> {
+    let candidates = candidate_frames(input, ROOM, &LargestCluster, 5);
+    let best = candidates.iter().map(|(seconds, _)| *seconds).take(1);
+    let mut output = candidates
+        .iter()
+        .map(|(seconds, score)| format!("\nSecond {seconds}: largest cluster of {score} tiles"))
+        .join("");
+
+    for (_, frame) in dump_frames(input, ROOM, best, FrameFormat::Text)? {
+        output.push('\n');
+        output.push_str(&String::from_utf8(frame)?);
+    }
+
+    Ok(output)
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
+    use itertools::chain;
+
+    use crate::utils::frame_score::{Entropy, LongestHorizontalRun, Variance};
 
     use super::*;
 
@@ -104,6 +231,129 @@ mod tests {
         assert_eq!(Some(12), part1_with_dimensions(&parse(EXAMPLE1).unwrap(), (7, 11)));
     }
 
+    /// Places a filled rectangle of robots that only lines up after `seconds`, among `noise`
+    /// robots that wander randomly.
+    fn hidden_picture((height, width): (usize, usize), seconds: isize, noise: usize) -> Input {
+        let (height, width) = (height as isize, width as isize);
+        let mut state = 0x2024_u64;
+        let mut random = |bound: isize| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as isize % bound
+        };
+
+        let picture = (10..16).cartesian_product(12..20).map(|(i, j)| {
+            let (vi, vj) = (random(2 * height) - height, random(2 * width) - width);
+            (((i - vi * seconds).rem_euclid(height), (j - vj * seconds).rem_euclid(width)), (vi, vj))
+        }).collect_vec();
+        let noise = (0..noise).map(|_| {
+            ((random(height), random(width)), (random(2 * height) - height, random(2 * width) - width))
+        }).collect_vec();
+
+        chain!(picture, noise).collect()
+    }
+
+    #[test]
+    fn candidate_frames_hidden_picture() {
+        let input = hidden_picture((31, 37), 500, 40);
+
+        assert_eq!(500, candidate_frames(&input, (31, 37), &Variance, 3)[0].0);
+        assert_eq!(500, candidate_frames(&input, (31, 37), &Entropy::new(4).unwrap(), 3)[0].0);
+        assert_eq!(500, candidate_frames(&input, (31, 37), &LargestCluster, 3)[0].0);
+        assert_eq!(500, candidate_frames(&input, (31, 37), &LongestHorizontalRun, 3)[0].0);
+        assert_eq!(Some(500), part2_with_dimensions(&input, (31, 37)));
+    }
+
+    #[test]
+    fn entropy_block_size() {
+        assert_eq!("Entropy block size must be positive", Entropy::new(0).err().unwrap().to_string());
+    }
+
+    #[test]
+    fn candidate_frames_custom_scorer() {
+        let input = parse(EXAMPLE1).unwrap();
+        let occupied_cells = |frame: &Grid<usize>| -(frame.iter().filter(|(_, &c)| c > 0).count() as f64);
+        let candidates = candidate_frames(&input, (7, 11), &occupied_cells, 2);
+
+        assert_eq!(2, candidates.len());
+        assert!(candidates[0].1 >= candidates[1].1);
+        assert!(candidates[0].1 > -12.0);
+    }
+
+    #[test]
+    fn dump_frames_example1_text() {
+        let frames = dump_frames(&parse(EXAMPLE1).unwrap(), (7, 11), [100], FrameFormat::Text).unwrap();
+
+        assert_eq!(vec![(100, indoc! {"
+            ......2..1.
+            ...........
+            1..........
+            .11........
+            .....1.....
+            ...12......
+            .1....1....
+        "}.as_bytes().to_vec())], frames);
+    }
+
+    #[test]
+    fn dump_frames_example1_images() {
+        let input = parse(EXAMPLE1).unwrap();
+        let pbm = dump_frames(&input, (7, 11), [100], FrameFormat::Pbm).unwrap();
+
+        assert_eq!(indoc! {"
+            P1
+            11 7
+            00000010010
+            00000000000
+            10000000000
+            01100000000
+            00000100000
+            00011000000
+            01000010000
+        "}.as_bytes(), pbm[0].1);
+
+        let png = dump_frames(&input, (7, 11), 0..3, FrameFormat::Png { scale: 4 }).unwrap();
+        assert_eq!(vec![0, 1, 2], png.iter().map(|(s, _)| *s).collect_vec());
+
+        let reader = png::Decoder::new(png[0].1.as_slice()).read_info().unwrap();
+        assert_eq!((44, 28), (reader.info().width, reader.info().height));
+    }
+
+    #[test]
+    fn dump_frames_wide_pbm() {
+        let input = vec![((3, 0), (0, 0)), ((3, 100), (0, 0))];
+        let pbm = String::from_utf8(dump_frames(&input, (7, 101), [0], FrameFormat::Pbm).unwrap().remove(0).1).unwrap();
+        let lines = pbm.lines().collect_vec();
+
+        assert_eq!(["P1", "101 7"], lines[..2]);
+        assert_eq!(2 + 7 * 2, lines.len());
+        assert!(lines.iter().all(|line| line.len() <= 70));
+        assert_eq!(format!("1{}", "0".repeat(69)), lines[2 + 3 * 2]);
+        assert_eq!(format!("{}1", "0".repeat(30)), lines[2 + 3 * 2 + 1]);
+    }
+
+    #[test]
+    fn save_frames_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+        let directory = std::env::temp_dir().join(format!("aoc-2024-day14-{}", std::process::id()));
+        let frames = dump_frames(&input, (7, 11), [5, 100], FrameFormat::Pbm).unwrap();
+
+        save_frames(&directory, &frames, FrameFormat::Pbm).unwrap();
+
+        assert_eq!(frames[1].1, fs::read(directory.join("second-00100.pbm")).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn animate_example1() {
+        let mut output = Vec::new();
+        animate(&parse(EXAMPLE1).unwrap(), (7, 11), 99..101, &mut output, Duration::ZERO).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(2, output.matches("\x1b[H\x1b[2J").count());
+        assert!(output.contains("Second 99\n"));
+        assert!(output.ends_with(".1....1....\n"));
+    }
+
     #[test]
     fn part1_input() {
         assert_eq!(Some(232253028), part1(&parse(include_str!("../input/2024/day14.txt")).unwrap()));
@@ -1,10 +1,15 @@
+use std::fmt::Debug;
+use std::iter::{successors, zip};
+use std::ops::RangeInclusive;
+
 use anyhow::{bail, Context, Error, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
+use hashbrown::HashSet;
 use itertools::{chain, Itertools};
 
-use crate::utils::grid::{Direction, Grid, Position};
+use crate::utils::grid::{Direction, Grid, IntoEnumIterator, Position};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Tile {
     Free,
     Box,
@@ -54,6 +59,196 @@ fn parse(input: &str) -> Result<Input> {
     Ok((grid, start_position, movements?))
 }
 
+trait WarehouseTile: Copy + PartialEq + Debug + Into<char> {
+    const FREE: Self;
+
+    /// Returns the cells that have to move for the robot to step onto `position`, or `None` if
+    /// the push is blocked.
+    fn pushed_cells(grid: &Grid<Self>, position: Position, direction: Direction) -> Option<Vec<Position>>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Change<T> {
+    position: Position,
+    before: T,
+    after: T,
+}
+
+/// The effect of a single robot movement, enough to replay it in either direction.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Move<T> {
+    direction: Direction,
+    from: Position,
+    to: Position,
+    changes: Vec<Change<T>>,
+}
+
+#[derive(Clone)]
+struct Warehouse<T> where T: Clone {
+    grid: Grid<T>,
+    robot: Position,
+}
+
+impl<T> Warehouse<T> where T: WarehouseTile {
+    fn new(grid: Grid<T>, robot: Position) -> Warehouse<T> {
+        Warehouse { grid, robot }
+    }
+
+    fn check_robot(&self) -> Result<()> {
+        match self.grid.get(&self.robot) {
+            Some(tile) if *tile == T::FREE => Ok(()),
+            Some(tile) => bail!("Robot at {:?} overlaps {tile:?}", self.robot),
+            None => bail!("Robot at {:?} is outside the warehouse", self.robot),
+        }
+    }
+
+    fn step(&mut self, direction: Direction) -> Result<Move<T>> {
+        self.check_robot()?;
+
+        let from = self.robot;
+        let candidate_position = from.step(direction);
+
+        let pushed_cells = self.grid
+            .get(&candidate_position)
+            .and_then(|_| T::pushed_cells(&self.grid, candidate_position, direction));
+
+        let Some(cells) = pushed_cells else {
+            return Ok(Move { direction, from, to: from, changes: Vec::new() });
+        };
+
+        let cells = cells.into_iter().unique().collect_vec();
+        let tiles = cells.iter().map(|p| *self.grid.get(p).unwrap()).collect_vec();
+        let touched = chain!(cells.iter().copied(), cells.iter().map(|p| p.step(direction)))
+            .unique()
+            .collect_vec();
+        let before = touched.iter().map(|p| *self.grid.get(p).unwrap()).collect_vec();
+
+        for p in &cells {
+            self.grid.set(p, T::FREE);
+        }
+
+        for (p, t) in zip(&cells, tiles) {
+            self.grid.set(&p.step(direction), t);
+        }
+
+        let changes = zip(touched, before)
+            .map(|(position, before)| Change { position, before, after: *self.grid.get(&position).unwrap() })
+            .filter(|change| change.before != change.after)
+            .sorted_by_key(|change| change.position)
+            .collect_vec();
+
+        self.robot = candidate_position;
+
+        Ok(Move { direction, from, to: candidate_position, changes })
+    }
+
+    fn apply(&mut self, movement: &Move<T>) {
+        for change in &movement.changes {
+            self.grid.set(&change.position, change.after);
+        }
+
+        self.robot = movement.to;
+    }
+
+    fn revert(&mut self, movement: &Move<T>) {
+        for change in &movement.changes {
+            self.grid.set(&change.position, change.before);
+        }
+
+        self.robot = movement.from;
+    }
+
+    fn cell(&self, position: Position, tile: T) -> char {
+        if position == self.robot { '@' } else { tile.into() }
+    }
+
+    fn render(&self) -> String {
+        self.grid.render(|p, t| self.cell(p, *t))
+    }
+}
+
+/// A recorded run of the robot: the starting warehouse plus one `Move` per movement.
+struct Simulation<T> where T: Clone {
+    initial: Warehouse<T>,
+    moves: Vec<Move<T>>,
+}
+
+impl<T> Simulation<T> where T: WarehouseTile {
+    fn run(warehouse: Warehouse<T>, movements: &[Direction]) -> Result<Simulation<T>> {
+        let initial = warehouse.clone();
+        let mut warehouse = warehouse;
+        let moves = movements.iter().map(|&direction| warehouse.step(direction)).collect::<Result<_>>()?;
+
+        Ok(Simulation { initial, moves })
+    }
+
+    fn replay(&self) -> Replay<'_, T> {
+        Replay { simulation: self, state: self.initial.clone(), cursor: 0 }
+    }
+
+    /// Renders the warehouse as it is after each number of moves in `cursors`, for example
+    /// `0..=moves.len()` for every frame of the run. `render` can produce text or an image.
+    fn frames<F>(&self, cursors: RangeInclusive<usize>, render: impl Fn(&Warehouse<T>) -> F) -> Vec<F> {
+        let mut replay = self.replay();
+        let mut frames = vec![];
+        replay.seek(*cursors.start());
+
+        while cursors.contains(&replay.cursor) {
+            frames.push(render(&replay.state));
+
+            if replay.forward().is_none() {
+                break;
+            }
+        }
+
+        frames
+    }
+
+    fn final_state(&self) -> Warehouse<T> {
+        let mut replay = self.replay();
+        replay.seek(self.moves.len());
+
+        replay.state
+    }
+}
+
+/// A cursor over a `Simulation`, positioned after `cursor` moves have been applied.
+struct Replay<'a, T> where T: Clone {
+    simulation: &'a Simulation<T>,
+    state: Warehouse<T>,
+    cursor: usize,
+}
+
+impl<'a, T> Replay<'a, T> where T: WarehouseTile {
+    fn forward(&mut self) -> Option<&'a Move<T>> {
+        let movement = self.simulation.moves.get(self.cursor)?;
+        self.state.apply(movement);
+        self.cursor += 1;
+
+        Some(movement)
+    }
+
+    fn backward(&mut self) -> Option<&'a Move<T>> {
+        let movement = &self.simulation.moves[self.cursor.checked_sub(1)?];
+        self.state.revert(movement);
+        self.cursor -= 1;
+
+        Some(movement)
+    }
+
+    fn seek(&mut self, cursor: usize) {
+        let cursor = cursor.min(self.simulation.moves.len());
+
+        while self.cursor < cursor {
+            self.forward();
+        }
+
+        while self.cursor > cursor {
+            self.backward();
+        }
+    }
+}
+
 fn find_free_position(grid: &Grid<Tile>, position: Position, direction: Direction) -> Option<Position> {
     let mut position = position;
 
@@ -72,121 +267,199 @@ fn find_free_position(grid: &Grid<Tile>, position: Position, direction: Directio
     None
 }
 
-#[aoc(day15, part1)]
-fn part1((grid, start_position, movements): &Input) -> usize {
-    let mut grid = grid.clone();
-    let mut position = *start_position;
-
-    for &direction in movements {
-        let candidate_position = position.step(direction);
-        match grid.get(&candidate_position) {
-            Some(Tile::Free) => { position = candidate_position; },
-            Some(Tile::Wall) | None => {},
-            Some(Tile::Box) => {
-                if let Some(free_position) = find_free_position(&grid, position, direction) {
-                    grid.set(&free_position, Tile::Box);
-                    grid.set(&candidate_position, Tile::Free);
-                    position = candidate_position;
-                }
-            }
+impl WarehouseTile for Tile {
+    const FREE: Self = Tile::Free;
+
+    fn pushed_cells(grid: &Grid<Tile>, position: Position, direction: Direction) -> Option<Vec<Position>> {
+        match grid.get(&position)? {
+            Tile::Free => Some(Vec::new()),
+            Tile::Wall => None,
+            Tile::Box => {
+                let free_position = find_free_position(grid, position, direction)?;
+
+                Some(successors(Some(position), |p| Some(p.step(direction)))
+                    .take_while(|p| *p != free_position)
+                    .collect())
+            },
         }
     }
+}
+
+fn simulate_part1((grid, start_position, movements): &Input) -> Result<Simulation<Tile>> {
+    Simulation::run(Warehouse::new(grid.clone(), *start_position), movements)
+}
 
-    grid.into_iter()
+#[aoc(day15, part1)]
+fn part1(input: &Input) -> Result<usize> {
+    let warehouse = simulate_part1(input)?.final_state();
+
+    Ok(warehouse.grid.into_iter()
         .filter(|(_, t)| *t == Tile::Box)
         .map(|(Position(i, j), _)| i as usize * 100 + j as usize)
-        .sum()
+        .sum())
 }
 
+/// A cell of a warehouse whose boxes are arbitrary polyominoes. All cells of a box share its id,
+/// and the glyph is only used for rendering.
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 enum Tile2 {
     #[default]
     Free,
     Wall,
-    BoxLeft,
-    BoxRight,
+    Object { id: usize, glyph: char },
 }
 
-fn find_pushable_boxes(grid: &Grid<Tile2>, position: Position, direction: Direction) -> Option<Vec<Position>> {
-    let positions = match (direction, grid.get(&position)) {
-        (Direction::Up | Direction::Down, Some(Tile2::BoxLeft)) => vec![position, position.step(Direction::Right)],
-        (Direction::Up | Direction::Down, Some(Tile2::BoxRight)) => vec![position.step(Direction::Left), position],
-        (Direction::Left | Direction::Right, Some(Tile2::BoxLeft | Tile2::BoxRight)) => vec![position],
-        (_, t @ (Some(Tile2::Free | Tile2::Wall) | None)) => panic!("Position must point to a BoxLeft or BoxRight: {t:?}"),
-    };
-
-    let candidate_positions = positions.iter().map(|p| p.step(direction)).collect_vec();
-    let candidate_tiles = candidate_positions
-        .iter()
-        .map(|p| grid.get(p))
-        .collect::<Option<Vec<_>>>()?;
-
-    match candidate_tiles[..] {
-        [Tile2::Wall] => None,
-        [Tile2::Wall, _] => None,
-        [_, Tile2::Wall] => None,
-        [Tile2::Free] => Some(positions),
-        [Tile2::Free, Tile2::Free] => Some(positions),
-        [Tile2::BoxLeft | Tile2::BoxRight] => Some(chain!(positions, find_pushable_boxes(grid, candidate_positions[0], direction)?).collect_vec()),
-        [Tile2::BoxRight, Tile2::Free] => Some(chain!(positions, find_pushable_boxes(grid, candidate_positions[0], direction)?).collect_vec()),
-        [Tile2::Free, Tile2::BoxLeft] => Some(chain!(positions, find_pushable_boxes(grid, candidate_positions[1], direction)?).collect_vec()),
-        [Tile2::BoxLeft, Tile2::BoxRight] => Some(chain!(positions, find_pushable_boxes(grid, candidate_positions[0], direction)?).collect_vec()),
-        [Tile2::BoxRight, Tile2::BoxLeft] => Some(chain!(
-            positions,
-            find_pushable_boxes(grid, candidate_positions[0], direction)?,
-            find_pushable_boxes(grid, candidate_positions[1], direction)?,
-        ).collect_vec()),
-        _ => unreachable!("Unexpected candidates: {candidate_tiles:?}"),
+impl From<Tile2> for char {
+    fn from(value: Tile2) -> Self {
+        match value {
+            Tile2::Free => '.',
+            Tile2::Wall => '#',
+            Tile2::Object { glyph, .. } => glyph,
+        }
     }
 }
 
-#[aoc(day15, part2)]
-fn part2((original_grid, start_position, movements): &Input) -> usize {
-    let mut grid = Grid::<Tile2>::new(original_grid.rows(), original_grid.cols::<isize>() * 2);
-    let mut position = Position(start_position.0, start_position.1 * 2);
-
-    for (Position(i, j), ot) in original_grid {
-        let (t1, t2) = match ot {
-            Tile::Free => (Tile2::Free, Tile2::Free),
-            Tile::Box => (Tile2::BoxLeft, Tile2::BoxRight),
-            Tile::Wall => (Tile2::Wall, Tile2::Wall),
-        };
+fn object_cells(grid: &Grid<Tile2>, position: Position, id: usize) -> Vec<Position> {
+    let mut cells = vec![position];
+    let mut seen = HashSet::<Position>::from_iter([position]);
+    let mut index = 0;
 
-        grid.set(&Position(i, j * 2), t1);
-        grid.set(&Position(i, j * 2 + 1), t2);
+    while let Some(&cell) = cells.get(index) {
+        index += 1;
+
+        for neighbor in Direction::iter().map(|d| cell.step(d)) {
+            if matches!(grid.get(&neighbor), Some(&Tile2::Object { id: other, .. }) if other == id) && seen.insert(neighbor) {
+                cells.push(neighbor);
+            }
+        }
     }
 
-    for &direction in movements {
-        let candidate_position = position.step(direction);
-        match grid.get(&candidate_position) {
-            Some(Tile2::Free) => { position = candidate_position; },
-            Some(Tile2::Wall) | None => {},
-            Some(Tile2::BoxLeft | Tile2::BoxRight) => {
-                if let Some(positions) = find_pushable_boxes(&grid, candidate_position, direction) {
-                    let boxes = positions
-                        .iter()
-                        .unique()
-                        .map(|p| (*p, *grid.get(p).unwrap()))
-                        .collect_vec();
+    cells
+}
 
-                    for (p, _) in &boxes {
-                        grid.set(p, Tile2::Free);
-                    }
+impl WarehouseTile for Tile2 {
+    const FREE: Self = Tile2::Free;
 
-                    for &(p, t) in &boxes {
-                        grid.set(&p.step(direction), t);
-                    }
+    fn pushed_cells(grid: &Grid<Tile2>, position: Position, direction: Direction) -> Option<Vec<Position>> {
+        let (first_position, first_id) = match grid.get(&position)? {
+            Tile2::Free => return Some(Vec::new()),
+            Tile2::Wall => return None,
+            &Tile2::Object { id, .. } => (position, id),
+        };
 
-                    position = candidate_position;
+        let mut pending = vec![(first_position, first_id)];
+        let mut pushed_ids = HashSet::<usize>::from_iter([first_id]);
+        let mut pushed_cells = Vec::new();
+
+        while let Some((position, id)) = pending.pop() {
+            for cell in object_cells(grid, position, id) {
+                let target = cell.step(direction);
+
+                match grid.get(&target)? {
+                    Tile2::Free => {},
+                    Tile2::Wall => return None,
+                    &Tile2::Object { id: other, .. } => {
+                        if pushed_ids.insert(other) {
+                            pending.push((target, other));
+                        }
+                    },
                 }
+
+                pushed_cells.push(cell);
+            }
+        }
+
+        Some(pushed_cells)
+    }
+}
+
+impl Warehouse<Tile2> {
+    /// Builds a warehouse from `grid`, checking that every object forms a single connected piece
+    /// and that the robot starts on a free cell.
+    fn with_objects(grid: Grid<Tile2>, robot: Position) -> Result<Warehouse<Tile2>> {
+        let object_sizes = grid.iter()
+            .filter_map(|(position, tile)| match *tile {
+                Tile2::Object { id, .. } => Some((id, position)),
+                _ => None,
+            })
+            .into_group_map();
+
+        for (id, cells) in object_sizes {
+            if object_cells(&grid, cells[0], id).len() != cells.len() {
+                bail!("Object {id} is split into disconnected pieces");
             }
         }
+
+        let warehouse = Warehouse::new(grid, robot);
+        warehouse.check_robot()?;
+
+        Ok(warehouse)
     }
 
-    grid.into_iter()
-        .filter(|(_, t)| *t == Tile2::BoxLeft)
-        .map(|(Position(i, j), _)| i as usize * 100 + j as usize)
-        .sum()
+    /// Sums the GPS coordinates of every object, measured from the top-left corner of its
+    /// bounding box.
+    fn gps_sum(&self) -> usize {
+        self.grid.iter()
+            .filter_map(|(position, tile)| match *tile {
+                Tile2::Object { id, .. } => Some((id, position)),
+                _ => None,
+            })
+            .into_grouping_map()
+            .fold((isize::MAX, isize::MAX), |(top, left), _, Position(i, j)| (top.min(i), left.min(j)))
+            .into_values()
+            .map(|(top, left)| top as usize * 100 + left as usize)
+            .sum()
+    }
+}
+
+/// Stretches every cell of `original_grid` horizontally into `width` cells, turning each box into
+/// a 1 by `width` object.
+fn scale(original_grid: &Grid<Tile>, start_position: Position, width: usize) -> Result<Warehouse<Tile2>> {
+    if width == 0 {
+        bail!("Unable to scale the warehouse to width 0");
+    }
+
+    let mut grid = Grid::<Tile2>::new(original_grid.rows(), original_grid.cols::<usize>() * width);
+    let glyphs = match width {
+        1 => vec!['O'],
+        _ => chain!(['['], vec!['='; width - 2], [']']).collect_vec(),
+    };
+
+    for (id, (Position(i, j), ot)) in original_grid.into_iter().enumerate() {
+        for (k, &glyph) in glyphs.iter().enumerate() {
+            let tile = match ot {
+                Tile::Free => Tile2::Free,
+                Tile::Box => Tile2::Object { id, glyph },
+                Tile::Wall => Tile2::Wall,
+            };
+
+            grid.set(&Position(i, j * width as isize + k as isize), tile);
+        }
+    }
+
+    Warehouse::with_objects(grid, Position(start_position.0, start_position.1 * width as isize))
+}
+
+fn widen(original_grid: &Grid<Tile>, start_position: Position) -> Result<Warehouse<Tile2>> {
+    scale(original_grid, start_position, 2)
+}
+
+fn simulate_part2((original_grid, start_position, movements): &Input) -> Result<Simulation<Tile2>> {
+    Simulation::run(widen(original_grid, *start_position)?, movements)
+}
+
+#[aoc(day15, part2)]
+fn part2(input: &Input) -> Result<usize> {
+    Ok(simulate_part2(input)?.final_state().gps_sum())
+}
+
+/// Draws the wide warehouse after the last move, for checking the final layout by eye.
+#[allow(dead_code)]
+fn part2_render(input: &Input) -> Result<String> {
+    let simulation = simulate_part2(input)?;
+    let last = simulation.moves.len();
+
+    Ok(simulation.frames(last..=last, |warehouse| format!("\n{}", warehouse.render())).concat())
 }
 
 #[cfg(test)]
@@ -219,6 +492,23 @@ mod tests {
         v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
     "};
 
+    #[test]
+    fn part2_render_example1() {
+        assert_eq!(indoc! {"
+
+            ####################
+            ##[].......[].[][]##
+            ##[]...........[].##
+            ##[]........[][][]##
+            ##[]......[]....[]##
+            ##..##......[]....##
+            ##..[]............##
+            ##..@......[].[][]##
+            ##......[][]..[]..##
+            ####################
+        "}, part2_render(&parse(EXAMPLE1).unwrap()).unwrap());
+    }
+
     const EXAMPLE2: &str = indoc! {"
         ########
         #..O.O.#
@@ -244,33 +534,277 @@ mod tests {
         <vv<<^^<<^^
     "};
 
+    fn object(id: usize, glyph: char) -> Tile2 {
+        Tile2::Object { id, glyph }
+    }
+
+    /// Parses a warehouse where every letter is a cell of the object named by that letter.
+    fn parse_objects(input: &str) -> Result<Warehouse<Tile2>> {
+        let rows = input.lines().count();
+        let cols = input.lines().map(str::len).max().unwrap_or(0);
+        let mut grid = Grid::<Tile2>::new(rows, cols);
+        let mut robot = None;
+
+        for (i, line) in input.lines().enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                let tile = match c {
+                    '.' => Tile2::Free,
+                    '#' => Tile2::Wall,
+                    '@' => { robot = Some(Position::new(i, j)); Tile2::Free },
+                    c => object(c as usize, c),
+                };
+
+                grid.set(&Position::new(i, j), tile);
+            }
+        }
+
+        Warehouse::with_objects(grid, robot.context("No robot found")?)
+    }
+
     #[test]
     fn part1_example1() {
-        assert_eq!(10092, part1(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(10092, part1(&parse(EXAMPLE1).unwrap()).unwrap());
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(2028, part1(&parse(EXAMPLE2).unwrap()));
+        assert_eq!(2028, part1(&parse(EXAMPLE2).unwrap()).unwrap());
     }
 
     #[test]
     fn part1_input() {
-        assert_eq!(1415498, part1(&parse(include_str!("../input/2024/day15.txt")).unwrap()));
+        assert_eq!(1415498, part1(&parse(include_str!("../input/2024/day15.txt")).unwrap()).unwrap());
     }
 
     #[test]
     fn part2_example1() {
-        assert_eq!(9021, part2(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(9021, part2(&parse(EXAMPLE1).unwrap()).unwrap());
     }
 
     #[test]
     fn part2_example3() {
-        assert_eq!(105 + 207 + 306, part2(&parse(EXAMPLE3).unwrap()));
+        assert_eq!(105 + 207 + 306, part2(&parse(EXAMPLE3).unwrap()).unwrap());
     }
 
     #[test]
     fn part2_input() {
-        assert_eq!(1432898, part2(&parse(include_str!("../input/2024/day15.txt")).unwrap()));
+        assert_eq!(1432898, part2(&parse(include_str!("../input/2024/day15.txt")).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn replay_example2() {
+        let (grid, start_position, movements) = parse(EXAMPLE2).unwrap();
+        let simulation = Simulation::run(Warehouse::new(grid, start_position), &movements).unwrap();
+        let frames = simulation.frames(0..=movements.len(), Warehouse::render);
+
+        assert_eq!(movements.len() + 1, frames.len());
+        assert_eq!(indoc! {"
+            ########
+            #..@OO.#
+            ##..O..#
+            #...O..#
+            #.#.O..#
+            #...O..#
+            #......#
+            ########
+        "}, frames[4]);
+        assert_eq!(indoc! {"
+            ########
+            #....OO#
+            ##.....#
+            #.....O#
+            #.#O@..#
+            #...O..#
+            #...O..#
+            ########
+        "}, frames[movements.len()]);
+
+        let mut replay = simulation.replay();
+        replay.seek(movements.len());
+        assert_eq!(frames[movements.len()], replay.state.render());
+
+        while replay.backward().is_some() {
+            assert_eq!(frames[replay.cursor], replay.state.render());
+        }
+
+        assert_eq!(0, replay.cursor);
+        assert_eq!(frames[3..=5], simulation.frames(3..=5, Warehouse::render));
+    }
+
+    #[test]
+    fn replay_moves_example3() {
+        let (grid, start_position, movements) = parse(EXAMPLE3).unwrap();
+        let simulation = Simulation::run(widen(&grid, start_position).unwrap(), &movements).unwrap();
+        let id_at = |position| match simulation.initial.grid.get(&position) {
+            Some(&Tile2::Object { id, .. }) => id,
+            tile => panic!("No object at {position:?}: {tile:?}"),
+        };
+        let (left, right) = (id_at(Position(3, 6)), id_at(Position(3, 8)));
+
+        assert_eq!(
+            Move {
+                direction: Direction::Left,
+                from: Position(3, 10),
+                to: Position(3, 9),
+                changes: vec![
+                    Change { position: Position(3, 5), before: Tile2::Free, after: object(left, '[') },
+                    Change { position: Position(3, 6), before: object(left, '['), after: object(left, ']') },
+                    Change { position: Position(3, 7), before: object(left, ']'), after: object(right, '[') },
+                    Change { position: Position(3, 8), before: object(right, '['), after: object(right, ']') },
+                    Change { position: Position(3, 9), before: object(right, ']'), after: Tile2::Free },
+                ],
+            },
+            simulation.moves[0],
+        );
+
+        let mut replay = simulation.replay();
+        replay.seek(6);
+        assert_eq!(indoc! {"
+            ##############
+            ##......##..##
+            ##...[][]...##
+            ##....[]....##
+            ##.....@....##
+            ##..........##
+            ##############
+        "}, replay.state.render());
+
+        replay.seek(5);
+        assert_eq!(indoc! {"
+            ##############
+            ##......##..##
+            ##..........##
+            ##...[][]...##
+            ##....[]....##
+            ##.....@....##
+            ##############
+        "}, replay.state.render());
+    }
+
+    #[test]
+    fn image_frames_example2() {
+        let (grid, start_position, movements) = parse(EXAMPLE2).unwrap();
+        let simulation = Simulation::run(Warehouse::new(grid, start_position), &movements).unwrap();
+        let frames = simulation.frames(0..=movements.len(), |warehouse| {
+            warehouse.grid.render_ppm(2, |p, t| match warehouse.cell(p, *t) {
+                '@' => [220, 40, 40],
+                '#' => [96, 96, 96],
+                '.' => [16, 16, 16],
+                _ => [200, 150, 60],
+            })
+        });
+
+        assert_eq!(movements.len() + 1, frames.len());
+        assert!(frames[0].starts_with(b"P6\n16 16\n255\n"));
+        assert_eq!(b"P6\n16 16\n255\n".len() + 16 * 16 * 3, frames[0].len());
+    }
+
+    #[test]
+    fn scaled_width1_example1() {
+        let (grid, start_position, movements) = parse(EXAMPLE1).unwrap();
+        let mut warehouse = scale(&grid, start_position, 1).unwrap();
+
+        for direction in movements {
+            warehouse.step(direction).unwrap();
+        }
+
+        assert_eq!(10092, warehouse.gps_sum());
+    }
+
+    #[test]
+    fn scaled_width3_example3() {
+        let (grid, start_position, movements) = parse(EXAMPLE3).unwrap();
+        let simulation = Simulation::run(scale(&grid, start_position, 3).unwrap(), &movements).unwrap();
+
+        assert_eq!(indoc! {"
+            #####################
+            ###.......@.###...###
+            ###........[=]....###
+            ###....[=]........###
+            ###......[=]......###
+            ###...............###
+            #####################
+        "}, simulation.final_state().render());
+    }
+
+    #[test]
+    fn polyomino_pushes() {
+        let mut warehouse = parse_objects(indoc! {"
+            #########
+            #.......#
+            #..AA.B.#
+            #...ABB.#
+            #...CC..#
+            #....@..#
+            #########
+        "}).unwrap();
+
+        let pushed = warehouse.step(Direction::Up).unwrap();
+        assert_eq!(Position(4, 5), pushed.to);
+        assert_eq!(indoc! {"
+            #########
+            #..AA.B.#
+            #...ABB.#
+            #...CC..#
+            #....@..#
+            #.......#
+            #########
+        "}, warehouse.render());
+
+        let blocked = warehouse.step(Direction::Up).unwrap();
+        assert_eq!(Position(4, 5), blocked.to);
+        assert!(blocked.changes.is_empty());
+
+        warehouse.step(Direction::Right).unwrap();
+        warehouse.step(Direction::Up).unwrap();
+        assert_eq!(indoc! {"
+            #########
+            #..AA.B.#
+            #...ABB.#
+            #...CC@.#
+            #.......#
+            #.......#
+            #########
+        "}, warehouse.render());
+    }
+
+    #[test]
+    fn polyomino_gps_sum() {
+        let warehouse = parse_objects(indoc! {"
+            ######
+            #..A.#
+            #.AAB#
+            #..@B#
+            ######
+        "}).unwrap();
+
+        assert_eq!(102 + 204, warehouse.gps_sum());
+    }
+
+    #[test]
+    fn scaled_width0() {
+        let (grid, start_position, _) = parse(EXAMPLE2).unwrap();
+
+        assert_eq!("Unable to scale the warehouse to width 0", scale(&grid, start_position, 0).err().unwrap().to_string());
+    }
+
+    #[test]
+    fn polyomino_disconnected() {
+        let error = parse_objects(indoc! {"
+            #####
+            #A.A#
+            #.@.#
+            #####
+        "}).err().unwrap();
+
+        assert_eq!("Object 65 is split into disconnected pieces", error.to_string());
+    }
+
+    #[test]
+    fn robot_overlapping_wall() {
+        let (grid, _, _) = parse(EXAMPLE2).unwrap();
+        let mut warehouse = Warehouse::new(grid, Position(0, 0));
+
+        assert_eq!("Robot at Position(0, 0) overlaps Wall", warehouse.step(Direction::Up).unwrap_err().to_string());
     }
 }
@@ -80,6 +80,8 @@ fn part2(input: &Input) -> Option<usize> {
 mod tests {
     use indoc::indoc;
 
+    use crate::utils::path_finding::{k_shortest_paths, paths_within_slack};
+
     use super::*;
 
     const EXAMPLE1: &str = indoc! {"
@@ -149,4 +151,49 @@ mod tests {
     fn part2_input() {
         assert_eq!(Some(483), part2(&parse(include_str!("../input/2024/day16.txt")).unwrap()));
     }
+
+    #[test]
+    fn k_shortest_paths_example1() {
+        let (grid, start, goal) = parse(EXAMPLE1).unwrap();
+        let paths = k_shortest_paths(
+            (start, Direction::Right),
+            |node| neighbors(&grid, node),
+            |(position, _)| position == goal,
+            5,
+        );
+
+        let within_slack = paths_within_slack(
+            (start, Direction::Right),
+            |node| neighbors(&grid, node),
+            |(position, _)| position == goal,
+            2002,
+        );
+
+        assert_eq!(vec![7036, 7036, 7036, 9038, 9038], paths.iter().map(|(distance, _)| *distance).collect_vec());
+        assert_eq!(5, paths.iter().map(|(_, path)| path).unique().count());
+        assert_eq!(
+            within_slack.iter().map(|(distance, _)| *distance).take(5).collect_vec(),
+            paths.iter().map(|(distance, _)| *distance).collect_vec(),
+        );
+    }
+
+    #[test]
+    fn paths_within_slack_example1() {
+        let (grid, start, goal) = parse(EXAMPLE1).unwrap();
+        let paths = paths_within_slack(
+            (start, Direction::Right),
+            |node| neighbors(&grid, node),
+            |(position, _)| position == goal,
+            0,
+        );
+
+        let num_positions = paths
+            .iter()
+            .flat_map(|(_, path)| path.iter().map(|(position, _)| position))
+            .unique()
+            .count();
+
+        assert_eq!(3, paths.len());
+        assert_eq!(Some(num_positions), part2(&parse(EXAMPLE1).unwrap()));
+    }
 }
@@ -1,14 +1,17 @@
+use std::collections::HashSet;
 use std::ops::{Index, IndexMut};
 
-use anyhow::{bail, ensure, Context, Error, Result};
+use anyhow::{bail, ensure, Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 use derive_more::derive::TryFrom;
 use itertools::Itertools;
 use scan_fmt::scan_fmt;
+use strum::{Display, EnumString};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, TryFrom, Display, EnumString)]
 #[try_from(repr)]
 #[repr(usize)]
+#[strum(serialize_all = "lowercase")]
 enum Instruction {
     Adv = 0,
     Bxl = 1,
@@ -22,7 +25,7 @@ enum Instruction {
 
 type Registers = [usize; 3];
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, TryFrom, Display, EnumString)]
 #[try_from(repr)]
 #[repr(usize)]
 enum Register {
@@ -45,6 +48,30 @@ impl IndexMut<Register> for Registers {
     }
 }
 
+/// Why the values at an address don't form an operation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DecodeError {
+    /// The program ends before the operation has an operand, after the given opcode if any.
+    Truncated(Option<usize>),
+    InvalidOpcode(usize),
+    OperandOutOfRange(usize),
+    InvalidComboOperand(usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Truncated(None) => write!(f, "Unable to parse operation, program segment to short: []"),
+            DecodeError::Truncated(Some(opcode)) => write!(f, "Unable to parse operation, program segment to short: [{opcode}]"),
+            DecodeError::InvalidOpcode(opcode) => write!(f, "Invalid opcode: {opcode}"),
+            DecodeError::OperandOutOfRange(value) => write!(f, "Operand out of range: {value}"),
+            DecodeError::InvalidComboOperand(value) => write!(f, "Invalid combo operand: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Operand {
     Literal(usize),
@@ -52,18 +79,18 @@ enum Operand {
 }
 
 impl TryFrom<(Instruction, usize)> for Operand {
-    type Error = Error;
+    type Error = DecodeError;
 
-    fn try_from(value: (Instruction, usize)) -> Result<Self> {
+    fn try_from(value: (Instruction, usize)) -> std::result::Result<Self, DecodeError> {
         use Instruction::*;
         use Operand::*;
 
         let operand = match value {
+            (_, v @ 8..) => return Err(DecodeError::OperandOutOfRange(v)),
             (Bxl | Jnz | Bxc, v) => Literal(v),
             (Adv | Bdv | Cdv | Bst | Out, v @ 0..=3) => Literal(v),
-            (Adv | Bdv | Cdv | Bst | Out, v @ 4..=6) => Register((v - 4).try_into()?),
-            (Adv | Bdv | Cdv | Bst | Out, 7) => bail!("Invalid combo operand: 7"),
-            _ => bail!("Invalid instruction/operand combination: {value:?}")
+            (Adv | Bdv | Cdv | Bst | Out, v @ 4..=6) => Register((v - 4).try_into().map_err(|_| DecodeError::InvalidComboOperand(v))?),
+            (Adv | Bdv | Cdv | Bst | Out, v) => return Err(DecodeError::InvalidComboOperand(v)),
         };
 
         Ok(operand)
@@ -77,6 +104,22 @@ impl Operand {
             Operand::Register(register) => registers[register],
         }
     }
+
+    fn encode(self) -> usize {
+        match self {
+            Operand::Literal(value) => value,
+            Operand::Register(register) => register as usize + 4,
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Literal(value) => write!(f, "{value}"),
+            Operand::Register(register) => write!(f, "{register}"),
+        }
+    }
 }
 
 type Input = (Registers, Vec<usize>);
@@ -98,71 +141,323 @@ fn parse(input: &str) -> Result<Input> {
     Ok((registers, program))
 }
 
-fn parse_operation(program: &[usize]) -> Result<(Instruction, Operand)> {
-    ensure!(program.len() >= 2, "Unable to parse operation, program segment to short: {program:?}");
+fn decode(program: &[usize]) -> std::result::Result<(Instruction, Operand), DecodeError> {
+    let &[opcode, operand, ..] = program else { return Err(DecodeError::Truncated(program.first().copied())) };
 
-    let instruction = program[0].try_into()?;
-    let operand = (instruction, program[1]).try_into()?;
+    let instruction = Instruction::try_from(opcode).map_err(|_| DecodeError::InvalidOpcode(opcode))?;
+    let operand = (instruction, operand).try_into()?;
 
     Ok((instruction, operand))
 }
 
-fn run_program(program: &[usize], registers: Registers) -> Result<(Vec<usize>, Registers)> {
-    let mut registers = registers;
-    let mut ip = 0;
-    let mut output: Vec<usize> = vec![];
+fn parse_operation(program: &[usize]) -> Result<(Instruction, Operand)> {
+    Ok(decode(program)?)
+}
 
-    loop {
-        if ip >= program.len() {
-            break;
-        }
+fn describe_operation(instruction: Instruction, operand: Operand) -> String {
+    match instruction {
+        Instruction::Adv => format!("A = A >> {operand}"),
+        Instruction::Bxl => format!("B = B ^ {operand}"),
+        Instruction::Bst => format!("B = {operand} % 8"),
+        Instruction::Jnz => format!("if A != 0 jump to {operand}"),
+        Instruction::Bxc => "B = B ^ C".to_string(),
+        Instruction::Out => format!("output {operand} % 8"),
+        Instruction::Bdv => format!("B = A >> {operand}"),
+        Instruction::Cdv => format!("C = A >> {operand}"),
+    }
+}
 
-        let (instruction, operand) = parse_operation(&program[ip..])?;
-        let value = operand.resolve_value(&registers);
+/// Lists the program one operation per line, with combo operands shown as the register or literal
+/// they refer to and a comment spelling out what the operation does.
+#[allow(dead_code)]
+fn disassemble(program: &[usize]) -> Result<String> {
+    ensure!(program.len().is_multiple_of(2), "Program has an odd number of values: {program:?}");
+
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(i, operation)| {
+            let (instruction, operand) = parse_operation(operation)?;
+            let code = format!("{instruction} {operand}");
+            let description = describe_operation(instruction, operand);
+
+            Ok(format!("{:02}: {code:<6} ; {description}\n", i * 2))
+        })
+        .collect()
+}
 
-        match instruction {
-            Instruction::Adv => {
-                let a = registers[Register::A];
-                registers[Register::A] = a / (1 << value);
-            }
-            Instruction::Bxl => {
-                let b = registers[Register::B];
-                registers[Register::B] = b ^ value;
-            }
-            Instruction::Bst => {
-                registers[Register::B] = value % 8;
-            }
-            Instruction::Jnz => {
-                let a = registers[Register::A];
+/// Turns a listing back into a program. Accepts the output of `disassemble`, where the address
+/// prefix and comments are optional.
+#[allow(dead_code)]
+fn assemble(source: &str) -> Result<Vec<usize>> {
+    source
+        .lines()
+        .map(|line| line.split_once(';').map_or(line, |(code, _)| code).trim())
+        .map(|line| line.split_once(':').map_or(line, |(_, code)| code).trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let Some((instruction, operand)) = line.split_whitespace().collect_tuple() else {
+                bail!("Unable to parse operation: {line}");
+            };
+
+            let instruction = instruction.parse::<Instruction>().context(format!("Unknown instruction: {instruction}"))?;
+            let operand = match operand.parse::<Register>() {
+                Ok(register) => Operand::Register(register),
+                Err(_) => Operand::Literal(operand.parse().context(format!("Unable to parse operand: {operand}"))?),
+            };
+
+            let encoded = [instruction as usize, operand.encode()];
+            ensure!(parse_operation(&encoded)? == (instruction, operand), "Invalid operand for {instruction}: {operand}");
+
+            Ok(encoded)
+        })
+        .flatten_ok()
+        .collect()
+}
 
-                if a != 0 {
-                    ip = value;
-                    continue;
-                }
-            }
-            Instruction::Bxc => {
-                let b = registers[Register::B];
-                let c = registers[Register::C];
-                registers[Register::B] = b ^ c;
+/// `value / 2^shift`, which is zero once the shift reaches the width of a register.
+fn shift_right(value: usize, shift: usize) -> usize {
+    u32::try_from(shift).ok().and_then(|shift| value.checked_shr(shift)).unwrap_or(0)
+}
+
+/// Applies a single operation and returns the value it outputs, if any. `ip` is moved past the
+/// operation, or to the jump target.
+fn execute(instruction: Instruction, operand: Operand, registers: &mut Registers, ip: &mut usize) -> Option<usize> {
+    let value = operand.resolve_value(registers);
+
+    *ip += 2;
+
+    match instruction {
+        Instruction::Adv => {
+            let a = registers[Register::A];
+            registers[Register::A] = shift_right(a, value);
+        }
+        Instruction::Bxl => {
+            let b = registers[Register::B];
+            registers[Register::B] = b ^ value;
+        }
+        Instruction::Bst => {
+            registers[Register::B] = value % 8;
+        }
+        Instruction::Jnz => {
+            let a = registers[Register::A];
+
+            if a != 0 {
+                *ip = value;
             }
-            Instruction::Out => {
-                output.push(value % 8);
+        }
+        Instruction::Bxc => {
+            let b = registers[Register::B];
+            let c = registers[Register::C];
+            registers[Register::B] = b ^ c;
+        }
+        Instruction::Out => {
+            return Some(value % 8);
+        }
+        Instruction::Bdv => {
+            let a = registers[Register::A];
+            registers[Register::B] = shift_right(a, value);
+        }
+        Instruction::Cdv => {
+            let a = registers[Register::A];
+            registers[Register::C] = shift_right(a, value);
+        }
+    }
+
+    None
+}
+
+/// A program decoded once up front, for running it many times. Every address is decoded, since a
+/// `jnz` may land on an odd one; addresses that don't decode only fail if they are reached.
+#[derive(Clone, Debug)]
+struct DecodedProgram {
+    operations: Vec<std::result::Result<(Instruction, Operand), DecodeError>>,
+}
+
+impl DecodedProgram {
+    fn new(program: &[usize]) -> DecodedProgram {
+        DecodedProgram {
+            operations: (0..program.len())
+                .map(|ip| decode(&program[ip..]))
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    fn operation(&self, ip: usize) -> Result<(Instruction, Operand)> {
+        Ok(self.operations[ip]?)
+    }
+
+    /// Runs until the program halts, or until `keep_going` returns false for the output so far.
+    fn run_while(&self, registers: Registers, step_limit: usize, keep_going: impl FnMut(&[usize]) -> bool) -> Result<(Vec<usize>, Registers)> {
+        let mut machine = Machine::new(self, registers);
+        machine.run_while(step_limit, keep_going)?;
+
+        Ok((machine.output, machine.registers))
+    }
+
+    fn run(&self, registers: Registers) -> Result<(Vec<usize>, Registers)> {
+        self.run_while(registers, STEP_LIMIT, |_| true)
+    }
+}
+
+/// Upper bound on executed instructions for `run_program`, so a program that never halts is
+/// reported as an error instead of hanging.
+const STEP_LIMIT: usize = 10_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct TraceEntry {
+    step: usize,
+    ip: usize,
+    instruction: Instruction,
+    operand: Operand,
+    before: Registers,
+    after: Registers,
+    output: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum StopReason {
+    Halted,
+    Breakpoint(usize),
+    OutputRejected,
+}
+
+/// Step-by-step interpreter over a `DecodedProgram`, with breakpoints and an optional trace.
+#[derive(Clone, Debug)]
+struct Machine<'a> {
+    program: &'a DecodedProgram,
+    ip: usize,
+    registers: Registers,
+    output: Vec<usize>,
+    steps: usize,
+    breakpoints: HashSet<usize>,
+    trace: Option<Vec<TraceEntry>>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(program: &'a DecodedProgram, registers: Registers) -> Machine<'a> {
+        Machine {
+            program,
+            ip: 0,
+            registers,
+            output: vec![],
+            steps: 0,
+            breakpoints: HashSet::new(),
+            trace: None,
+        }
+    }
+
+    fn with_trace(mut self) -> Machine<'a> {
+        self.trace = Some(vec![]);
+        self
+    }
+
+    fn is_halted(&self) -> bool {
+        self.ip >= self.program.len()
+    }
+
+    /// Executes the operation at `ip` and returns the value it outputs, if any. Does nothing if the
+    /// machine has already halted.
+    fn step(&mut self) -> Result<Option<usize>> {
+        if self.is_halted() {
+            return Ok(None);
+        }
+
+        let (instruction, operand) = self.program.operation(self.ip)?;
+        let before = self.registers;
+        let ip = self.ip;
+        let output = execute(instruction, operand, &mut self.registers, &mut self.ip);
+
+        self.output.extend(output);
+
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry { step: self.steps, ip, instruction, operand, before, after: self.registers, output });
+        }
+
+        self.steps += 1;
+
+        Ok(output)
+    }
+
+    /// Runs until the program halts, reaches a breakpoint, or `keep_going` returns false for the
+    /// output so far, executing at most `step_limit` operations in this call. The operation at the
+    /// current `ip` is always executed, so calling `run` again continues past the breakpoint that
+    /// stopped it.
+    fn run_while(&mut self, step_limit: usize, mut keep_going: impl FnMut(&[usize]) -> bool) -> Result<StopReason> {
+        let start = self.steps;
+
+        loop {
+            if self.is_halted() {
+                return Ok(StopReason::Halted);
             }
-            Instruction::Bdv => {
-                let a = registers[Register::A];
-                registers[Register::B] = a / (1 << value);
 
+            if self.steps > start && !self.breakpoints.is_empty() && self.breakpoints.contains(&self.ip) {
+                return Ok(StopReason::Breakpoint(self.ip));
             }
-            Instruction::Cdv => {
-                let a = registers[Register::A];
-                registers[Register::C] = a / (1 << value);
+
+            ensure!(self.steps - start < step_limit, "Step limit of {step_limit} reached without halting, at ip {}", self.ip);
+
+            if self.step()?.is_some() && !keep_going(&self.output) {
+                return Ok(StopReason::OutputRejected);
             }
         }
+    }
 
-        ip += 2;
+    fn run(&mut self, step_limit: usize) -> Result<StopReason> {
+        self.run_while(step_limit, |_| true)
     }
+}
 
-    Ok((output, registers))
+fn format_registers([a, b, c]: Registers) -> String {
+    format!("A={a} B={b} C={c}")
+}
+
+fn trace_to_text(trace: &[TraceEntry]) -> String {
+    trace
+        .iter()
+        .map(|entry| {
+            let output = entry.output.map(|value| format!(" out={value}")).unwrap_or_default();
+
+            format!(
+                "{} @{:02}: {} {} [{}] -> [{}]{output}\n",
+                entry.step,
+                entry.ip,
+                entry.instruction,
+                entry.operand,
+                format_registers(entry.before),
+                format_registers(entry.after),
+            )
+        })
+        .collect()
+}
+
+fn trace_to_json(trace: &[TraceEntry]) -> String {
+    let entries = trace
+        .iter()
+        .map(|entry| {
+            let output = entry.output.map_or("null".to_string(), |value| value.to_string());
+
+            format!(
+                "{{\"step\":{},\"ip\":{},\"instruction\":\"{}\",\"operand\":\"{}\",\"before\":[{}],\"after\":[{}],\"output\":{output}}}",
+                entry.step,
+                entry.ip,
+                entry.instruction,
+                entry.operand,
+                entry.before.iter().join(","),
+                entry.after.iter().join(","),
+            )
+        })
+        .join(",\n  ");
+
+    format!("[\n  {entries}\n]\n")
+}
+
+fn run_program(program: &[usize], registers: Registers) -> Result<(Vec<usize>, Registers)> {
+    DecodedProgram::new(program).run(registers)
 }
 
 #[aoc(day17, part1)]
@@ -173,23 +468,113 @@ fn part1(input: &Input) -> Result<String> {
     Ok(output.into_iter().join(","))
 }
 
-#[aoc(day17, part2)]
-fn part2(input: &Input) -> Result<usize> {
+#[allow(dead_code)]
+fn trace_program(program: &[usize], registers: Registers) -> Result<Vec<TraceEntry>> {
+    let program = DecodedProgram::new(program);
+    let mut machine = Machine::new(&program, registers).with_trace();
+    machine.run(STEP_LIMIT)?;
+
+    Ok(machine.trace.unwrap_or_default())
+}
+
+/// The execution trace of part 1, one operation per line.
+#[allow(dead_code)]
+fn part1_trace(input: &Input) -> Result<String> {
+    let (registers, program) = input;
+
+    Ok(format!("\n{}", trace_to_text(&trace_program(program, *registers)?)))
+}
+
+/// The execution trace of part 1 as a JSON array.
+#[allow(dead_code)]
+fn part1_trace_json(input: &Input) -> Result<String> {
     let (registers, program) = input;
-    let mut prefixes = vec![0];
 
-    for i in 0..program.len() {
+    Ok(trace_to_json(&trace_program(program, *registers)?))
+}
+
+/// The shape of program `find_register_a` can solve directly: a single loop ending in `jnz 0`
+/// that outputs one value per iteration, shifts `A` right by `shift` bits, and recomputes `B` and
+/// `C` from scratch every iteration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct LoopShape {
+    shift: usize,
+}
+
+fn analyze_loop(program: &[usize]) -> Result<LoopShape> {
+    use Instruction::*;
+
+    ensure!(program.len().is_multiple_of(2), "the program has an odd number of values");
+
+    let operations = program.chunks(2).map(parse_operation).collect::<Result<Vec<_>>>()?;
+
+    let Some(((Jnz, Operand::Literal(0)), body)) = operations.split_last() else {
+        bail!("the program doesn't end with `jnz 0` looping back to the start");
+    };
+
+    let jumps = body.iter().filter(|(instruction, _)| *instruction == Jnz).count();
+    ensure!(jumps == 0, "the loop body contains {jumps} more `jnz`, so it isn't a single loop");
+
+    let outputs = body.iter().filter(|(instruction, _)| *instruction == Out).count();
+    ensure!(outputs == 1, "the loop body has {outputs} `out` instructions instead of exactly one");
+
+    let shifts = body.iter().filter(|(instruction, _)| *instruction == Adv).collect_vec();
+    let shift = match shifts.as_slice() {
+        [(_, Operand::Literal(shift @ 1..))] => *shift,
+        [(_, operand)] => bail!("`A` is shifted by `{operand}` rather than by a non-zero constant"),
+        _ => bail!("`A` is shifted by {} `adv` instructions instead of exactly one", shifts.len()),
+    };
+
+    let mut written = HashSet::new();
+
+    for &(instruction, operand) in body {
+        let mut reads = match instruction {
+            Bxl => vec![Register::B],
+            Bxc => vec![Register::B, Register::C],
+            _ => vec![],
+        };
+
+        if let (Adv | Bst | Out | Bdv | Cdv, Operand::Register(register)) = (instruction, operand) {
+            reads.push(register);
+        }
+
+        if let Some(register) = reads.into_iter().find(|&register| register != Register::A && !written.contains(&register)) {
+            bail!("register {register} is read before it is written, so its value carries over between iterations");
+        }
+
+        match instruction {
+            Bxl | Bst | Bxc | Bdv => { written.insert(Register::B); },
+            Cdv => { written.insert(Register::C); },
+            _ => {},
+        }
+    }
+
+    Ok(LoopShape { shift })
+}
+
+/// Builds `A` from its most significant bits down. The last value output only depends on the top
+/// `shift` bits of `A`, the one before that on the top `2 * shift` bits, and so on, so every
+/// surviving prefix is extended by all possible next `shift` bits and checked against a single
+/// iteration of the loop body.
+fn solve_loop(program: &[usize], registers: Registers, target: &[usize], LoopShape { shift }: LoopShape) -> Result<Option<usize>> {
+    let body = DecodedProgram::new(&program[..program.len() - 2]);
+    let program = DecodedProgram::new(program);
+    let mut prefixes = vec![0usize];
+
+    for &expected in target.iter().rev() {
         let mut next_prefixes = vec![];
 
         for prefix in prefixes {
-            for candidate_suffix in 0..(1 << 3) {
-                if i == 0 && candidate_suffix == 0 { continue; }
+            let low = u32::try_from(shift)
+                .ok()
+                .and_then(|shift| prefix.checked_shl(shift))
+                .filter(|low| shift_right(*low, shift) == prefix)
+                .with_context(|| format!("Register A would need more than {} bits to output all {} values", usize::BITS, target.len()))?;
 
-                let candidate = (prefix << 3) + candidate_suffix;
-                let registers = [candidate, registers[Register::B], registers[Register::C]];
-                let (output, _) = run_program(program, registers)?;
+            for candidate in (low..=low + ((1 << shift) - 1)).filter(|&candidate| candidate != 0) {
+                let (output, after) = body.run([candidate, registers[Register::B], registers[Register::C]])?;
 
-                if output[..] == program[(program.len() - (i + 1))..] {
+                if output == [expected] && after[Register::A] == prefix {
                     next_prefixes.push(candidate);
                 }
             }
@@ -198,11 +583,61 @@ fn part2(input: &Input) -> Result<usize> {
         prefixes = next_prefixes;
     }
 
-    if prefixes.is_empty() {
-        bail!("No solution found");
+    for a in prefixes.into_iter().sorted() {
+        let (output, _) = program.run([a, registers[Register::B], registers[Register::C]])?;
+
+        if output == target {
+            return Ok(Some(a));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Total number of operations the fallback search may execute, summed over all candidates.
+const FALLBACK_STEP_BUDGET: usize = 10_000_000;
+
+/// Tries `A = 1, 2, ...`, abandoning each run as soon as its output diverges from `target`. All
+/// runs share a budget of `step_budget` operations, and `None` is returned once it is spent.
+fn brute_force_register_a(program: &[usize], registers: Registers, target: &[usize], step_budget: usize) -> Option<usize> {
+    let program = DecodedProgram::new(program);
+    let mut remaining = step_budget;
+    let mut a = 1;
+
+    while remaining > 0 {
+        let mut machine = Machine::new(&program, [a, registers[Register::B], registers[Register::C]]);
+        let stop = machine.run_while(remaining, |output| target.starts_with(output));
+
+        if matches!(stop, Ok(StopReason::Halted)) && machine.output == target {
+            return Some(a);
+        }
+
+        remaining = remaining.saturating_sub(machine.steps.max(1));
+        a += 1;
     }
 
-    Ok(prefixes[0])
+    None
+}
+
+/// Finds the smallest initial value of `A` that makes the program output `target`. Programs with
+/// the expected `LoopShape` are solved directly; anything else falls back to a search with a fixed
+/// step budget, and the error explains which part of the shape didn't match.
+fn find_register_a(program: &[usize], registers: Registers, target: &[usize]) -> Result<usize> {
+    match analyze_loop(program) {
+        Ok(shape) => solve_loop(program, registers, target, shape)?
+            .with_context(|| format!("No value of register A makes the program output {target:?}")),
+        Err(reason) => brute_force_register_a(program, registers, target, FALLBACK_STEP_BUDGET)
+            .with_context(|| format!(
+                "Unsupported program shape ({reason}), and a fallback search of {FALLBACK_STEP_BUDGET} steps found no value of register A that makes it output {target:?}",
+            )),
+    }
+}
+
+#[aoc(day17, part2)]
+fn part2(input: &Input) -> Result<usize> {
+    let (registers, program) = input;
+
+    find_register_a(program, *registers, program)
 }
 
 #[cfg(test)]
@@ -277,4 +712,211 @@ mod tests {
     fn part2_input() {
         assert_eq!(108107566389757, part2(&parse(include_str!("../input/2024/day17.txt")).unwrap()).unwrap());
     }
+
+    #[test]
+    fn disassemble_example2() {
+        let (_, program) = parse(EXAMPLE2).unwrap();
+
+        assert_eq!(indoc! {"
+            00: adv 3  ; A = A >> 3
+            02: out A  ; output A % 8
+            04: jnz 0  ; if A != 0 jump to 0
+        "}, disassemble(&program).unwrap());
+    }
+
+    #[test]
+    fn assemble_round_trip() {
+        let programs = [
+            parse(EXAMPLE1).unwrap().1,
+            parse(EXAMPLE2).unwrap().1,
+            vec![2, 4, 1, 1, 7, 5, 1, 5, 4, 0, 5, 5, 0, 3, 3, 0],
+        ];
+
+        for program in programs {
+            assert_eq!(program, assemble(&disassemble(&program).unwrap()).unwrap());
+        }
+
+        assert_eq!(vec![0, 3, 5, 4, 3, 0], assemble("adv 3\nout A ; comment\n\njnz 0").unwrap());
+    }
+
+    #[test]
+    fn assemble_invalid_operands() {
+        assert!(assemble("adv 4").is_err());
+        assert!(assemble("bxl A").is_err());
+        assert!(assemble("mul 3").is_err());
+        assert_eq!("Operand out of range: 9", assemble("bxl 9").unwrap_err().to_string());
+        assert!(disassemble(&[0, 7]).is_err());
+        assert!(disassemble(&[1, 9]).is_err());
+    }
+
+    #[test]
+    fn machine_breakpoints() {
+        let (registers, program) = parse(EXAMPLE1).unwrap();
+        let program = DecodedProgram::new(&program);
+        let mut machine = Machine::new(&program, registers);
+        machine.breakpoints.insert(4);
+
+        assert_eq!(StopReason::Breakpoint(4), machine.run(STEP_LIMIT).unwrap());
+        assert_eq!((4, [364, 0, 0], vec![4]), (machine.ip, machine.registers, machine.output.clone()));
+
+        assert_eq!(StopReason::Breakpoint(4), machine.run(STEP_LIMIT).unwrap());
+        assert_eq!((4, [182, 0, 0], vec![4, 6]), (machine.ip, machine.registers, machine.output.clone()));
+
+        machine.step().unwrap();
+        assert_eq!(0, machine.ip);
+
+        machine.breakpoints.clear();
+        assert_eq!(StopReason::Halted, machine.run(STEP_LIMIT).unwrap());
+        assert_eq!("4,6,3,5,6,3,5,2,1,0", machine.output.iter().join(","));
+    }
+
+    #[test]
+    fn machine_step_limit() {
+        let program = DecodedProgram::new(&[0, 1, 3, 0]);
+
+        assert!(run_program(&[3, 0], [1, 0, 0]).is_err());
+        assert!(Machine::new(&program, [1 << 20, 0, 0]).run(10).is_err());
+        assert!(Machine::new(&program, [1 << 20, 0, 0]).run(100).is_ok());
+    }
+
+    #[test]
+    fn machine_step_limit_per_run() {
+        let (registers, program) = parse(EXAMPLE1).unwrap();
+        let program = DecodedProgram::new(&program);
+        let mut machine = Machine::new(&program, registers);
+        machine.breakpoints.insert(4);
+
+        assert_eq!(StopReason::Breakpoint(4), machine.run(3).unwrap());
+        assert_eq!(StopReason::Breakpoint(4), machine.run(3).unwrap());
+        assert_eq!(StopReason::Breakpoint(4), machine.run(3).unwrap());
+        assert!(machine.run(2).is_err());
+        assert_eq!(10, machine.steps);
+    }
+
+    #[test]
+    fn machine_trace() {
+        let trace = trace_program(&[0, 1, 5, 4, 3, 0], [2, 0, 0]).unwrap();
+
+        assert_eq!(indoc! {"
+            0 @00: adv 1 [A=2 B=0 C=0] -> [A=1 B=0 C=0]
+            1 @02: out A [A=1 B=0 C=0] -> [A=1 B=0 C=0] out=1
+            2 @04: jnz 0 [A=1 B=0 C=0] -> [A=1 B=0 C=0]
+            3 @00: adv 1 [A=1 B=0 C=0] -> [A=0 B=0 C=0]
+            4 @02: out A [A=0 B=0 C=0] -> [A=0 B=0 C=0] out=0
+            5 @04: jnz 0 [A=0 B=0 C=0] -> [A=0 B=0 C=0]
+        "}, trace_to_text(&trace));
+
+        assert_eq!(indoc! {r#"
+            [
+              {"step":0,"ip":0,"instruction":"adv","operand":"1","before":[2,0,0],"after":[1,0,0],"output":null},
+              {"step":1,"ip":2,"instruction":"out","operand":"A","before":[1,0,0],"after":[1,0,0],"output":1}
+            ]
+        "#}, trace_to_json(&trace[..2]));
+    }
+
+    #[test]
+    fn part1_trace_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+        let text = part1_trace(&input).unwrap();
+        let json = part1_trace_json(&input).unwrap();
+
+        assert_eq!(Some("0 @00: adv 1 [A=729 B=0 C=0] -> [A=364 B=0 C=0]"), text.lines().nth(1));
+        assert_eq!(text.lines().count() - 1, json.lines().count() - 2);
+        assert!(json.starts_with("[\n  {\"step\":0,\"ip\":0,\"instruction\":\"adv\""));
+    }
+
+    #[test]
+    fn division_by_huge_powers_of_two() {
+        let program = assemble("adv B\nbdv C\ncdv C\nout A\nout B\nout C").unwrap();
+
+        assert_eq!(vec![0, 0, 0], run_program(&program, [5, 64, 200]).unwrap().0);
+        assert_eq!(vec![5, 0, 0], run_program(&program, [40, 3, 65]).unwrap().0);
+    }
+
+    #[test]
+    fn find_register_a_target_too_long() {
+        let program = assemble("bst A\nout B\nadv 3\njnz 0").unwrap();
+
+        assert_eq!(Some(1), find_register_a(&program, [0, 0, 0], &[1; 21]).ok().map(|a| a & 7));
+        assert_eq!(
+            "Register A would need more than 64 bits to output all 23 values",
+            find_register_a(&program, [0, 0, 0], &[1; 23]).unwrap_err().to_string(),
+        );
+    }
+
+    #[test]
+    fn find_register_a_other_shift() {
+        let program = assemble("bst A\nbxl 3\ncdv B\nbxc 0\nout B\nadv 2\njnz 0").unwrap();
+        let (target, _) = run_program(&program, [123_456_789, 0, 0]).unwrap();
+        let a = find_register_a(&program, [0, 0, 0], &target).unwrap();
+
+        assert_eq!(LoopShape { shift: 2 }, analyze_loop(&program).unwrap());
+        assert!(a <= 123_456_789);
+        assert_eq!(target, run_program(&program, [a, 0, 0]).unwrap().0);
+    }
+
+    #[test]
+    fn find_register_a_fallback() {
+        let program = assemble("out B\nbxl 1\nadv 3\njnz 0").unwrap();
+
+        assert_eq!(
+            "register B is read before it is written, so its value carries over between iterations",
+            analyze_loop(&program).unwrap_err().to_string(),
+        );
+        assert_eq!(64, find_register_a(&program, [0, 0, 0], &[0, 1, 0]).unwrap());
+        assert!(find_register_a(&program, [0, 0, 0], &[5]).unwrap_err().to_string().contains("register B is read before it is written"));
+    }
+
+    #[test]
+    fn find_register_a_fallback_budget() {
+        let program = assemble("out A\nbxl 1\njnz 0").unwrap();
+        let error = find_register_a(&program, [0, 0, 0], &[1, 1]).unwrap_err().to_string();
+
+        assert!(error.starts_with("Unsupported program shape (`A` is shifted by 0 `adv` instructions"), "{error}");
+        assert_eq!(None, brute_force_register_a(&program, [0, 0, 0], &[1, 1], 1000));
+    }
+
+    #[test]
+    fn analyze_loop_unexpected_shapes() {
+        let reason = |source: &str| analyze_loop(&assemble(source).unwrap()).unwrap_err().to_string();
+
+        assert_eq!("the program doesn't end with `jnz 0` looping back to the start", reason("adv 3\nout A"));
+        assert_eq!("the loop body has 2 `out` instructions instead of exactly one", reason("adv 3\nout A\nout A\njnz 0"));
+        assert_eq!("`A` is shifted by `B` rather than by a non-zero constant", reason("bst A\nadv B\nout A\njnz 0"));
+    }
+
+    #[test]
+    fn decoded_program_halting_on_last_allowed_step() {
+        let program = DecodedProgram::new(&[0, 1, 5, 4, 3, 0]);
+
+        assert_eq!((vec![1, 0], [0, 0, 0]), program.run_while([2, 0, 0], 6, |_| true).unwrap());
+        assert!(program.run_while([2, 0, 0], 5, |_| true).is_err());
+        assert_eq!(StopReason::Halted, Machine::new(&program, [2, 0, 0]).run(6).unwrap());
+        assert!(Machine::new(&program, [2, 0, 0]).run(5).is_err());
+    }
+
+    #[test]
+    fn decoded_program_errors() {
+        let program = DecodedProgram::new(&[3, 2, 0, 7, 9, 1]);
+
+        assert_eq!(
+            vec![
+                Ok((Instruction::Jnz, Operand::Literal(2))),
+                Ok((Instruction::Bst, Operand::Literal(0))),
+                Err(DecodeError::InvalidComboOperand(7)),
+                Err(DecodeError::OperandOutOfRange(9)),
+                Err(DecodeError::InvalidOpcode(9)),
+                Err(DecodeError::Truncated(Some(1))),
+            ],
+            program.operations,
+        );
+        assert_eq!("Invalid opcode: 9", program.operation(4).unwrap_err().to_string());
+        assert_eq!(Err(DecodeError::Truncated(None)), decode(&[]));
+    }
+
+    #[test]
+    fn decoded_program_odd_addresses() {
+        assert_eq!((vec![5], [5, 5, 0]), run_program(&[0, 1, 5, 4, 3, 1, 0], [10, 0, 0]).unwrap());
+        assert_eq!("Invalid combo operand: 7", run_program(&[3, 2, 0, 7], [1, 0, 0]).unwrap_err().to_string());
+    }
 }
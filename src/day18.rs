@@ -5,6 +5,7 @@ use itertools::Itertools;
 
 use crate::utils::grid::{Direction, Grid, Position, IntoEnumIterator};
 use crate::utils::path_finding::shortest_paths_to_target;
+use crate::utils::union_find::first_disconnecting_block;
 
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 enum MemoryCell {
@@ -79,27 +80,11 @@ fn part1(input: &Input) -> Option<usize> {
 
 #[aoc(day18, part2)]
 fn part2((grid, corrupted_positions): &Input) -> Option<String> {
-    let mut a = 1;
-    let mut b = corrupted_positions.len() - 1;
-
-    let time = loop {
-        if a > b { break None }
-
-        let time = (a + b) / 2;
-
-        match (distance_at_time(grid, time - 1), distance_at_time(grid, time)) {
-            (Some(_), Some(_)) => {
-                a = time + 1;
-            },
-            (None, None) => {
-                b = time - 1;
-            },
-            (Some(_), None) => { break Some(time) },
-            (None, Some(_)) => unreachable!(),
-        }
-    };
-
-    let Position(i, j) = corrupted_positions[time? - 1];
+    let source = Position(0, 0);
+    let target = Position(grid.rows::<isize>() - 1, grid.cols::<isize>() - 1);
+    let time = first_disconnecting_block(grid, |_| true, corrupted_positions, source, target)?;
+
+    let Position(i, j) = corrupted_positions[time];
     Some(format!("{j},{i}"))
 }
 
@@ -107,6 +92,8 @@ fn part2((grid, corrupted_positions): &Input) -> Option<String> {
 mod tests {
     use indoc::indoc;
 
+    use crate::utils::path_finding::{distance_to_target_observed, shortest_paths_to_target_observed, ExpansionCounter, ExpansionOrder, MaxQueueSize};
+
     use super::*;
 
     const EXAMPLE1: &str = indoc! {"
@@ -156,4 +143,52 @@ mod tests {
     fn part2_input() {
         assert_eq!("45,16", part2(&parse(include_str!("../input/2024/day18.txt")).unwrap()).unwrap());
     }
+
+    #[test]
+    fn expansion_counter_skips_stale_entries() {
+        // Node 1 is queued at distance 5 before the cheaper route through node 2 is found.
+        let edges = |node: usize| match node {
+            0 => vec![(1, 5), (2, 1)],
+            2 => vec![(1, 1)],
+            _ => vec![],
+        };
+        let mut counter = ExpansionCounter::default();
+        let distance = distance_to_target_observed(0, edges, |node| node == 1, &mut counter).unwrap();
+
+        assert_eq!(2, distance);
+        assert_eq!(3, counter.expansions);
+        assert_eq!(1, counter.targets);
+    }
+
+    #[test]
+    fn expansion_order_example1() {
+        let (grid, _) = parse_with_dimensions(EXAMPLE1, 7, 7).unwrap();
+        let mut observer = (ExpansionOrder::default(), (ExpansionCounter::default(), MaxQueueSize::default()));
+        let (distance, _) = shortest_paths_to_target_observed(
+            Position(0, 0),
+            |position| neighbors(&grid, position, 12),
+            |position| position == Position(6, 6),
+            &mut observer,
+        ).unwrap();
+
+        let (order, (counter, max_queue_size)) = observer;
+        let rendered = order.render(&grid, |position| position, |cell| match cell {
+            MemoryCell::Corrupted(t) if *t < 12 => '#',
+            _ => '.',
+        });
+
+        assert_eq!(22, distance);
+        assert_eq!(order.0.len(), counter.expansions);
+        assert_eq!(1, counter.targets);
+        assert!(max_queue_size.0 > 0);
+        assert_eq!(indoc! {"
+            025#ijk
+            14#gh#l
+            37af#om
+            69e#qp#
+            8d#sr#.
+            c#vt#..
+            #.#uwxy
+        "}, rendered);
+    }
 }
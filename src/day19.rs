@@ -1,5 +1,9 @@
+use std::ops::AddAssign;
+
 use anyhow::{Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+use num_traits::{One, Zero};
 
 type Input = (Vec<String>, Vec<String>);
 
@@ -12,54 +16,180 @@ fn parse(input: &str) -> Result<Input> {
     Ok((available, desired))
 }
 
-fn count_combinations(cache: &mut [Option<usize>], available_patterns: &[String], desired_pattern: &str) -> usize {
-    if desired_pattern.is_empty() {
-        return 1;
+#[derive(Clone, Default, Debug)]
+struct TrieNode {
+    children: Vec<(u8, usize)>,
+    pattern: Option<usize>,
+}
+
+/// Prefix tree over the available towel patterns, so all patterns matching at an offset are
+/// found in a single walk.
+#[derive(Clone, Debug)]
+struct Trie<'a> {
+    patterns: Vec<&'a str>,
+    nodes: Vec<TrieNode>,
+}
+
+impl<'a> Trie<'a> {
+    fn new(patterns: impl IntoIterator<Item = &'a str>) -> Trie<'a> {
+        let mut trie = Trie { patterns: Vec::new(), nodes: vec![TrieNode::default()] };
+
+        for pattern in patterns {
+            let mut node = 0;
+
+            for &byte in pattern.as_bytes() {
+                node = match trie.nodes[node].children.iter().find(|(b, _)| *b == byte) {
+                    Some(&(_, child)) => child,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.push((byte, child));
+                        child
+                    },
+                };
+            }
+
+            if trie.nodes[node].pattern.is_none() && !pattern.is_empty() {
+                trie.nodes[node].pattern = Some(trie.patterns.len());
+                trie.patterns.push(pattern);
+            }
+        }
+
+        trie
     }
 
-    if let Some(result) = cache[0] {
-        return result;
+    /// The lengths and indices of every pattern that matches `design` at `start`, shortest first.
+    fn matches_at<'s>(&'s self, design: &'s str, start: usize) -> impl Iterator<Item = (usize, usize)> + 's {
+        design.as_bytes()[start..]
+            .iter()
+            .scan(0, |node, &byte| {
+                *node = self.nodes[*node].children.iter().find(|(b, _)| *b == byte)?.1;
+                Some(*node)
+            })
+            .enumerate()
+            .filter_map(|(i, node)| self.nodes[node].pattern.map(|pattern| (i + 1, pattern)))
     }
 
-    let result = available_patterns
-        .iter()
-        .map(|candidate| {
-            match desired_pattern.starts_with(candidate) {
-                true => count_combinations(&mut cache[candidate.len()..], available_patterns, &desired_pattern[candidate.len()..]),
-                false => 0,
+    /// Whether the rest of `design` from every offset can be built from the patterns.
+    fn completable(&self, design: &str) -> Vec<bool> {
+        let mut completable = vec![false; design.len() + 1];
+        completable[design.len()] = true;
+
+        for start in (0..design.len()).rev() {
+            completable[start] = self.matches_at(design, start).any(|(len, _)| completable[start + len]);
+        }
+
+        completable
+    }
+
+    fn exists(&self, design: &str) -> bool {
+        self.completable(design)[0]
+    }
+
+    /// Number of arrangements of `design`, in any integer type wide enough to hold it.
+    fn count<T>(&self, design: &str) -> T where T: Zero + One + Clone + for<'b> AddAssign<&'b T> {
+        let mut ways = vec![T::zero(); design.len() + 1];
+        ways[design.len()] = T::one();
+
+        for start in (0..design.len()).rev() {
+            let mut total = T::zero();
+
+            for (len, _) in self.matches_at(design, start) {
+                total += &ways[start + len];
             }
-        })
-        .sum();
 
-    cache[0] = Some(result);
-    result
+            ways[start] = total;
+        }
+
+        ways.swap_remove(0)
+    }
+
+    /// Up to `limit` arrangements of `design`, preferring shorter patterns first.
+    fn arrangements(&self, design: &str, limit: usize) -> Vec<Vec<&'a str>> {
+        let completable = self.completable(design);
+
+        if !completable[0] {
+            return Vec::new();
+        }
+
+        let mut search = ArrangementSearch { trie: self, design, completable, limit, arrangement: Vec::new(), arrangements: Vec::new() };
+        search.extend(0);
+        search.arrangements
+    }
+}
+
+/// A depth-first walk over the arrangements of one design, only following patterns after which
+/// the rest of the design can still be completed.
+struct ArrangementSearch<'t, 'a> {
+    trie: &'t Trie<'a>,
+    design: &'t str,
+    completable: Vec<bool>,
+    limit: usize,
+    arrangement: Vec<&'a str>,
+    arrangements: Vec<Vec<&'a str>>,
+}
+
+impl ArrangementSearch<'_, '_> {
+    fn extend(&mut self, start: usize) {
+        if self.arrangements.len() >= self.limit {
+            return;
+        }
+
+        if start == self.design.len() {
+            self.arrangements.push(self.arrangement.clone());
+            return;
+        }
+
+        let (trie, design) = (self.trie, self.design);
+
+        for (len, pattern) in trie.matches_at(design, start) {
+            if self.completable[start + len] {
+                self.arrangement.push(trie.patterns[pattern]);
+                self.extend(start + len);
+                self.arrangement.pop();
+            }
+        }
+    }
 }
 
 #[aoc(day19, part1)]
 fn part1((available_patterns, desired_patterns): &Input) -> usize {
+    let trie = Trie::new(available_patterns.iter().map(String::as_str));
+
     desired_patterns
         .iter()
-        .filter(|desired_pattern| {
-            let mut cache = vec![None; desired_pattern.len()];
-            count_combinations(&mut cache, available_patterns, desired_pattern) > 0
-        })
+        .filter(|desired_pattern| trie.exists(desired_pattern))
         .count()
 }
 
 #[aoc(day19, part2)]
 fn part2((available_patterns, desired_patterns): &Input) -> usize {
+    let trie = Trie::new(available_patterns.iter().map(String::as_str));
+
     desired_patterns
         .iter()
-        .map(|desired_pattern| {
-            let mut cache = vec![None; desired_pattern.len()];
-            count_combinations(&mut cache, available_patterns, desired_pattern)
-        })
+        .map(|desired_pattern| trie.count::<usize>(desired_pattern))
         .sum()
 }
 
+/// Lists one arrangement for every design that can be made, as `design: pattern,pattern,...`.
+#[allow(dead_code)]
+fn part1_arrangements((available_patterns, desired_patterns): &Input) -> String {
+    let trie = Trie::new(available_patterns.iter().map(String::as_str));
+
+    desired_patterns
+        .iter()
+        .filter_map(|desired_pattern| {
+            let arrangement = trie.arrangements(desired_pattern, 1).pop()?;
+            Some(format!("{desired_pattern}: {}", arrangement.join(",")))
+        })
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
+    use num_bigint::BigUint;
 
     use super::*;
 
@@ -81,6 +211,45 @@ mod tests {
         assert_eq!(6, part1(&parse(EXAMPLE1).unwrap()));
     }
 
+    #[test]
+    fn trie_queries_example1() {
+        let (available, _) = parse(EXAMPLE1).unwrap();
+        let trie = Trie::new(available.iter().map(String::as_str));
+
+        assert!(trie.exists("brwrr"));
+        assert!(!trie.exists("ubwu"));
+        assert_eq!(2usize, trie.count("brwrr"));
+        assert_eq!(6u128, trie.count("rrbgbr"));
+        assert_eq!(BigUint::from(4u32), trie.count::<BigUint>("gbbr"));
+        assert_eq!(vec![vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]], trie.arrangements("brwrr", 10));
+        assert_eq!(vec![vec!["g", "b", "b", "r"], vec!["g", "b", "br"]], trie.arrangements("gbbr", 2));
+        assert!(trie.arrangements("bbrgwb", 10).is_empty());
+    }
+
+    #[test]
+    fn part1_arrangements_example1() {
+        let expected = indoc! {"
+            brwrr: b,r,wr,r
+            bggr: b,g,g,r
+            gbbr: g,b,b,r
+            rrbgbr: r,r,b,g,b,r
+            bwurrg: bwu,r,r,g
+            brgr: b,r,g,r
+        "};
+
+        assert_eq!(expected.trim_end(), part1_arrangements(&parse(EXAMPLE1).unwrap()));
+    }
+
+    #[test]
+    fn trie_count_overflow() {
+        let trie = Trie::new(["a", "aa"]);
+        let design = "a".repeat(200);
+
+        assert_eq!("453973694165307953197296969697410619233826", trie.count::<BigUint>(&design).to_string());
+        assert_eq!(trie.count::<BigUint>(&"a".repeat(150)), BigUint::from(trie.count::<u128>(&"a".repeat(150))));
+        assert_eq!(3, trie.arrangements(&design, 3).len());
+    }
+
     #[test]
     fn part1_input() {
         assert_eq!(304, part1(&parse(include_str!("../input/2024/day19.txt")).unwrap()));
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, Error, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 
@@ -46,7 +48,18 @@ fn neighbors(grid: &Grid<Tile>, position: SearchNode) -> Vec<(SearchNode, usize)
         .collect()
 }
 
-fn solve((grid, start, goal): &Input, maximum_cheat_length: usize, minimum_saved_time: usize) -> Option<usize> {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Cheat {
+    start: Position,
+    end: Position,
+    length: usize,
+    saved_time: usize,
+}
+
+/// Calls `visit` with every cheat of at most `maximum_cheat_length` that saves at least
+/// `minimum_saved_time`, in the order their start positions appear along the track. With a
+/// minimum of zero, moves that save nothing count too, including staying in place.
+fn visit_cheats((grid, start, goal): &Input, maximum_cheat_length: usize, minimum_saved_time: usize, mut visit: impl FnMut(Cheat)) -> Option<()> {
     let maximum_cheat_length = maximum_cheat_length as isize;
     let minimum_saved_time = minimum_saved_time as isize;
 
@@ -72,8 +85,6 @@ fn solve((grid, start, goal): &Input, maximum_cheat_length: usize, minimum_saved
         });
     }
 
-    let mut count = 0;
-
     for cheat_source in path {
         let Position(i, j) = cheat_source;
         let cheat_source_distance = distance_to_target.get(&cheat_source).unwrap().unwrap();
@@ -84,13 +95,57 @@ fn solve((grid, start, goal): &Input, maximum_cheat_length: usize, minimum_saved
                 let cheat_target = Position(i + oi, j + oj);
                 let Some(Some(cheat_target_distance)) = distance_to_target.get(&cheat_target) else { continue };
 
-                if cheat_source_distance - (cheat_target_distance + cheat_length) >= minimum_saved_time {
-                    count += 1;
+                let saved_time = cheat_source_distance - (cheat_target_distance + cheat_length);
+
+                if saved_time >= minimum_saved_time {
+                    visit(Cheat {
+                        start: cheat_source,
+                        end: cheat_target,
+                        length: cheat_length as usize,
+                        saved_time: saved_time as usize,
+                    });
                 }
             }
         }
     }
 
+    Some(())
+}
+
+#[allow(dead_code)]
+fn find_cheats(input: &Input, maximum_cheat_length: usize, minimum_saved_time: usize) -> Option<Vec<Cheat>> {
+    let mut cheats = vec![];
+    visit_cheats(input, maximum_cheat_length, minimum_saved_time, |cheat| cheats.push(cheat))?;
+    Some(cheats)
+}
+
+/// Number of cheats for every amount of saved time.
+#[allow(dead_code)]
+fn savings_histogram(cheats: &[Cheat]) -> BTreeMap<usize, usize> {
+    cheats
+        .iter()
+        .map(|cheat| cheat.saved_time)
+        .fold(BTreeMap::new(), |mut histogram, saved_time| {
+            *histogram.entry(saved_time).or_default() += 1;
+            histogram
+        })
+}
+
+/// Describes a histogram the way the puzzle lists its examples.
+#[allow(dead_code)]
+fn format_histogram(histogram: &BTreeMap<usize, usize>) -> String {
+    histogram
+        .iter()
+        .map(|(saved_time, count)| match count {
+            1 => format!("There is one cheat that saves {saved_time} picoseconds.\n"),
+            _ => format!("There are {count} cheats that save {saved_time} picoseconds.\n"),
+        })
+        .collect()
+}
+
+fn solve(input: &Input, maximum_cheat_length: usize, minimum_saved_time: usize) -> Option<usize> {
+    let mut count = 0;
+    visit_cheats(input, maximum_cheat_length, minimum_saved_time, |_| count += 1)?;
     Some(count)
 }
 
@@ -143,6 +198,68 @@ mod tests {
         assert_eq!(Some(1), solve(&parse(EXAMPLE1).unwrap(), 2, 64));
     }
 
+    #[test]
+    fn savings_histogram_part1_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+        let cheats = find_cheats(&input, 2, 1).unwrap();
+
+        assert_eq!(indoc! {"
+            There are 14 cheats that save 2 picoseconds.
+            There are 14 cheats that save 4 picoseconds.
+            There are 2 cheats that save 6 picoseconds.
+            There are 4 cheats that save 8 picoseconds.
+            There are 2 cheats that save 10 picoseconds.
+            There are 3 cheats that save 12 picoseconds.
+            There is one cheat that saves 20 picoseconds.
+            There is one cheat that saves 36 picoseconds.
+            There is one cheat that saves 38 picoseconds.
+            There is one cheat that saves 40 picoseconds.
+            There is one cheat that saves 64 picoseconds.
+        "}, format_histogram(&savings_histogram(&cheats)));
+
+        assert_eq!(
+            vec![Cheat { start: Position(7, 7), end: Position(7, 5), length: 2, saved_time: 64 }],
+            cheats.iter().filter(|cheat| cheat.saved_time == 64).copied().collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn savings_histogram_part2_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+        let histogram = savings_histogram(&find_cheats(&input, 20, 50).unwrap());
+
+        assert_eq!(
+            BTreeMap::from([(50, 32), (52, 31), (54, 29), (56, 39), (58, 25), (60, 23), (62, 20), (64, 19), (66, 12), (68, 14), (70, 12), (72, 22), (74, 4), (76, 3)]),
+            histogram,
+        );
+    }
+
+    #[test]
+    fn cumulative_counts_example1() {
+        let input = parse(EXAMPLE1).unwrap();
+        let part1 = [(1, 44), (2, 44), (3, 30), (4, 30), (6, 16), (8, 14), (10, 10), (12, 8), (13, 5), (20, 5), (36, 4), (38, 3), (40, 2), (64, 1), (65, 0)];
+        let part2 = [(50, 285), (52, 253), (54, 222), (56, 193), (58, 154), (60, 129), (62, 106), (64, 86), (66, 67), (68, 55), (70, 41), (72, 29), (74, 7), (76, 3), (77, 0)];
+
+        for (maximum_cheat_length, counts) in [(2, &part1), (20, &part2)] {
+            for &(minimum_saved_time, count) in counts {
+                assert_eq!(Some(count), solve(&input, maximum_cheat_length, minimum_saved_time), "saving at least {minimum_saved_time}");
+            }
+        }
+    }
+
+    #[test]
+    fn cheats_saving_nothing() {
+        let input = parse(EXAMPLE1).unwrap();
+
+        // Every one of the 85 track positions can "cheat" to itself without saving any time.
+        assert_eq!(Some(85), solve(&input, 0, 0));
+        assert_eq!(Some(0), solve(&input, 0, 1));
+
+        // On top of the 44 cheats that save time, staying put or following the track for one or
+        // two steps saves nothing.
+        assert_eq!(Some(44 + 85 + 84 + 83), solve(&input, 2, 0));
+    }
+
     #[test]
     fn part1_input() {
         assert_eq!(1518, part1(&parse(include_str!("../input/2024/day20.txt")).unwrap()).unwrap());
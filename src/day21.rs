@@ -1,253 +1,287 @@
-use std::iter::{once, repeat_n};
+use std::collections::VecDeque;
 
-use anyhow::{bail, Error, Result};
+use anyhow::{bail, Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 use hashbrown::HashMap;
 use itertools::Itertools;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum NumPadKey {
-    A,
-    Zero,
-    One,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
+use crate::utils::grid::{Direction, IntoEnumIterator, Position};
+
+const NUMERIC_KEYPAD: &str = "789\n456\n123\n 0A";
+const DIRECTIONAL_KEYPAD: &str = " ^A\n<v>";
+const SEQUENCE_LIMIT: usize = 1 << 24;
+
+/// A keypad read from a text layout, one character per key and a space for every gap.
+#[derive(Clone, Debug)]
+struct Keypad {
+    keys: HashMap<char, Position>,
+    cells: HashMap<Position, char>,
 }
 
-impl TryFrom<char> for NumPadKey {
-    type Error = Error;
-
-    fn try_from(value: char) -> Result<Self> {
-        match value {
-            'A' => Ok(NumPadKey::A),
-            '0' => Ok(NumPadKey::Zero),
-            '1' => Ok(NumPadKey::One),
-            '2' => Ok(NumPadKey::Two),
-            '3' => Ok(NumPadKey::Three),
-            '4' => Ok(NumPadKey::Four),
-            '5' => Ok(NumPadKey::Five),
-            '6' => Ok(NumPadKey::Six),
-            '7' => Ok(NumPadKey::Seven),
-            '8' => Ok(NumPadKey::Eight),
-            '9' => Ok(NumPadKey::Nine),
-            _ => bail!("Unrecognized NumPadKey: {value}"),
+impl Keypad {
+    fn parse(layout: &str) -> Result<Keypad> {
+        let mut keys = HashMap::new();
+        let mut cells = HashMap::new();
+
+        for (i, line) in layout.lines().enumerate() {
+            for (j, key) in line.chars().enumerate().filter(|(_, key)| *key != ' ') {
+                let position = Position::new(i, j);
+
+                if keys.insert(key, position).is_some() {
+                    bail!("Duplicate key in keypad layout: {key}");
+                }
+
+                cells.insert(position, key);
+            }
         }
-    }
-}
 
-impl TryFrom<(isize, isize)> for NumPadKey {
-    type Error = Error;
-
-    fn try_from(value: (isize, isize)) -> Result<Self> {
-        match value {
-            (0, 0) => Ok(NumPadKey::Seven),
-            (0, 1) => Ok(NumPadKey::Eight),
-            (0, 2) => Ok(NumPadKey::Nine),
-            (1, 0) => Ok(NumPadKey::Four),
-            (1, 1) => Ok(NumPadKey::Five),
-            (1, 2) => Ok(NumPadKey::Six),
-            (2, 0) => Ok(NumPadKey::One),
-            (2, 1) => Ok(NumPadKey::Two),
-            (2, 2) => Ok(NumPadKey::Three),
-            (3, 1) => Ok(NumPadKey::Zero),
-            (3, 2) => Ok(NumPadKey::A),
-            _ => panic!("Unrecognized NumPadKey: {value:?}"),
+        if keys.is_empty() {
+            bail!("Keypad layout has no keys");
         }
-    }
-}
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum DPadKey {
-    A,
-    Up,
-    Right,
-    Down,
-    Left,
-}
+        Ok(Keypad { keys, cells })
+    }
 
-impl TryFrom<char> for DPadKey {
-    type Error = Error;
-
-    fn try_from(value: char) -> Result<Self> {
-        match value {
-            'A' => Ok(DPadKey::A),
-            '^' => Ok(DPadKey::Up),
-            '>' => Ok(DPadKey::Right),
-            'v' => Ok(DPadKey::Down),
-            '<' => Ok(DPadKey::Left),
-            _ => bail!("Unrecognized NumPadKey: {value}"),
-        }
+    fn position(&self, key: char) -> Result<Position> {
+        self.keys.get(&key).copied().with_context(|| format!("Key not on keypad: {key}"))
     }
-}
 
-impl TryFrom<(isize, isize)> for DPadKey {
-    type Error = Error;
-
-    fn try_from(value: (isize, isize)) -> Result<Self> {
-        match value {
-            (0, 1) => Ok(DPadKey::Up),
-            (0, 2) => Ok(DPadKey::A),
-            (1, 0) => Ok(DPadKey::Left),
-            (1, 1) => Ok(DPadKey::Down),
-            (1, 2) => Ok(DPadKey::Right),
-            _ => panic!("Unrecognized DPadKey: {value:?}"),
+    /// Every shortest way of moving the arm from `from` to `to` without crossing a gap, as the
+    /// directional keys to press followed by `A`.
+    fn paths(&self, from: char, to: char) -> Result<Vec<String>> {
+        let target = self.position(to)?;
+        let mut distances = HashMap::from_iter([(target, 0)]);
+        let mut queue = VecDeque::from([target]);
+
+        while let Some(position) = queue.pop_front() {
+            for next in Direction::iter().map(|direction| position.step(direction)) {
+                if self.cells.contains_key(&next) && !distances.contains_key(&next) {
+                    distances.insert(next, distances[&position] + 1);
+                    queue.push_back(next);
+                }
+            }
         }
-    }
-}
 
-type Input = Vec<(String, Vec<NumPadKey>, usize)>;
+        let start = self.position(from)?;
 
-#[aoc_generator(day21)]
-fn parse(input: &str) -> Result<Input> {
-    input
-        .lines()
-        .map(|line| Ok((
-            line.to_string(),
-            line.chars().map(NumPadKey::try_from).collect::<Result<_>>()?,
-            line[0..line.len() - 1].parse::<usize>()?,
-        )))
-        .collect()
-}
+        if !distances.contains_key(&start) {
+            bail!("No path between keys {from} and {to}");
+        }
 
-type NumPadLut = HashMap<(NumPadKey, NumPadKey), Vec<Vec<DPadKey>>>;
-type DPadLut = HashMap<(DPadKey, DPadKey), Vec<Vec<DPadKey>>>;
+        let mut paths = Vec::new();
+        self.extend_paths(&distances, start, &mut String::new(), &mut paths);
 
-fn generate_numpad_lut() -> NumPadLut {
-    let mut nplut: NumPadLut = HashMap::new();
+        Ok(paths)
+    }
 
-    for start in (0..4isize).cartesian_product(0..3isize) {
-        if start == (3, 0) { continue; }
+    fn extend_paths(&self, distances: &HashMap<Position, usize>, position: Position, path: &mut String, paths: &mut Vec<String>) {
+        if distances[&position] == 0 {
+            paths.push(format!("{path}A"));
+            return;
+        }
 
-        for end in (0..4isize).cartesian_product(0..3isize) {
-            if end == (3, 0) { continue; }
+        for (direction, key) in [(Direction::Up, '^'), (Direction::Right, '>'), (Direction::Down, 'v'), (Direction::Left, '<')] {
+            let next = position.step(direction);
 
-            let (si, sj) = start;
-            let (ei, ej) = end;
+            if distances.get(&next).is_some_and(|&distance| distance + 1 == distances[&position]) {
+                path.push(key);
+                self.extend_paths(distances, next, path, paths);
+                path.pop();
+            }
+        }
+    }
+}
 
-            let mut paths = vec![];
+/// A chain of keypads where every keypad is operated by a robot whose arm is controlled from the
+/// next keypad in the chain. The code is typed on the first keypad and the last one is pressed
+/// directly.
+struct KeypadChain {
+    layers: Vec<Keypad>,
+    costs: Vec<HashMap<(char, char), usize>>,
+    #[allow(dead_code)]
+    moves: Vec<HashMap<(char, char), String>>,
+}
 
-            let idiff = si.abs_diff(ei);
-            let jdiff = sj.abs_diff(ej);
+impl KeypadChain {
+    fn new(layers: Vec<Keypad>) -> Result<KeypadChain> {
+        let Some(last) = layers.last() else { bail!("Keypad chain has no keypads") };
 
-            let ikey = if si > ei { DPadKey::Up } else { DPadKey::Down };
-            let jkey = if sj > ej { DPadKey::Left } else { DPadKey::Right };
+        if !layers[0].keys.contains_key(&'A') {
+            bail!("Keypad is missing key: A");
+        }
 
-            if (ei, sj) != (3, 0) {
-                paths.push(repeat_n(ikey, idiff).chain(repeat_n(jkey, jdiff)).chain(once(DPadKey::A)).collect_vec());
+        for keypad in &layers[1..] {
+            if let Some(key) = "^>v<A".chars().find(|key| !keypad.keys.contains_key(key)) {
+                bail!("Controlling keypad is missing key: {key}");
             }
+        }
+
+        let mut costs = vec![last.keys.keys().copied().cartesian_product(last.keys.keys().copied()).map(|pair| (pair, 1)).collect()];
+        let mut moves = vec![HashMap::new()];
+
+        for keypad in layers.iter().rev().skip(1) {
+            let next_costs = costs.last().unwrap();
+            let mut layer_costs = HashMap::new();
+            let mut layer_moves = HashMap::new();
 
-            if (si, ej) != (3, 0) {
-                paths.push(repeat_n(jkey, jdiff).chain(repeat_n(ikey, idiff)).chain(once(DPadKey::A)).collect_vec());
+            for (&from, &to) in keypad.keys.keys().cartesian_product(keypad.keys.keys()) {
+                let (cost, path) = keypad.paths(from, to)?
+                    .into_iter()
+                    .map(|path| (sequence_cost(next_costs, &path), path))
+                    .min_by_key(|(cost, _)| *cost)
+                    .unwrap();
+
+                layer_costs.insert((from, to), cost);
+                layer_moves.insert((from, to), path);
             }
 
-            nplut.insert((start.try_into().unwrap(), end.try_into().unwrap()), paths.into_iter().unique().collect_vec());
+            costs.push(layer_costs);
+            moves.push(layer_moves);
         }
-    }
 
-    nplut
-}
+        costs.reverse();
+        moves.reverse();
 
-fn generate_dpad_lut() -> DPadLut {
-    let mut dplut: DPadLut = HashMap::new();
+        Ok(KeypadChain { layers, costs, moves })
+    }
 
-    for start in (0..2isize).cartesian_product(0..3isize) {
-        if start == (0, 0) { continue; }
+    /// A numeric keypad operated through `robots` directional keypads, plus the one pressed by
+    /// hand.
+    fn door(robots: usize) -> Result<KeypadChain> {
+        let directional = Keypad::parse(DIRECTIONAL_KEYPAD)?;
+        let layers = std::iter::once(Keypad::parse(NUMERIC_KEYPAD)?)
+            .chain(std::iter::repeat_n(directional, robots + 1))
+            .collect();
 
-        for end in (0..2isize).cartesian_product(0..3isize) {
-            if end == (0, 0) { continue; }
+        KeypadChain::new(layers)
+    }
 
-            let (si, sj) = start;
-            let (ei, ej) = end;
+    fn check_code(&self, code: &str) -> Result<()> {
+        code.chars().try_for_each(|key| self.layers[0].position(key).map(|_| ()))
+    }
 
-            let mut paths = vec![];
+    /// Number of presses on the last keypad needed to type `code` on the first one.
+    fn shortest_length(&self, code: &str) -> Result<usize> {
+        self.check_code(code)?;
 
-            let idiff = si.abs_diff(ei);
-            let jdiff = sj.abs_diff(ej);
+        Ok(sequence_cost(&self.costs[0], code))
+    }
 
-            let ikey = if si > ei { DPadKey::Up } else { DPadKey::Down };
-            let jkey = if sj > ej { DPadKey::Left } else { DPadKey::Right };
+    /// Length of the keys typed on every keypad of the chain when `code` is entered with the
+    /// sequence from `shortest_sequence`, without building the sequence itself.
+    #[allow(dead_code)]
+    fn layer_lengths(&self, code: &str) -> Result<Vec<usize>> {
+        self.check_code(code)?;
 
-            if (ei, sj) != (0, 0) {
-                paths.push(repeat_n(ikey, idiff).chain(repeat_n(jkey, jdiff)).chain(once(DPadKey::A)).collect_vec());
-            }
+        let mut pairs: HashMap<(char, char), usize> = "A".chars().chain(code.chars()).tuple_windows().counts().into_iter().collect();
+        let mut lengths = vec![code.len()];
+
+        for moves in &self.moves[..self.layers.len() - 1] {
+            let mut next_pairs = HashMap::new();
 
-            if (si, ej) != (0, 0) {
-                paths.push(repeat_n(jkey, jdiff).chain(repeat_n(ikey, idiff)).chain(once(DPadKey::A)).collect_vec());
+            for (pair, count) in pairs {
+                for next_pair in "A".chars().chain(moves[&pair].chars()).tuple_windows() {
+                    *next_pairs.entry(next_pair).or_default() += count;
+                }
             }
 
-            dplut.insert((start.try_into().unwrap(), end.try_into().unwrap()), paths.into_iter().unique().collect_vec());
+            lengths.push(next_pairs.values().sum());
+            pairs = next_pairs;
         }
-    }
 
-    dplut
-}
-
-fn find_best_dpad_sequence(cache: &mut HashMap<(Vec<DPadKey>, usize), usize>, dplut: &DPadLut, dpad_keys: &Vec<DPadKey>, n: usize) -> usize {
-    if n == 0 { return dpad_keys.len(); }
-
-    if let Some(cached_result) = cache.get(&(dpad_keys.clone(), n)) {
-        return *cached_result;
+        Ok(lengths)
     }
 
-    let mut count = 0;
-    let mut current = DPadKey::A;
+    /// One shortest sequence of presses on the last keypad that types `code` on the first one.
+    /// Its length grows exponentially with the depth of the chain, so deep chains are refused.
+    #[allow(dead_code)]
+    fn shortest_sequence(&self, code: &str) -> Result<String> {
+        let length = self.shortest_length(code)?;
 
-    for &next in dpad_keys {
-        let mut best = usize::MAX;
+        if length > SEQUENCE_LIMIT {
+            bail!("Sequence too long to build: {length} presses");
+        }
+
+        let mut keys = code.to_string();
 
-        for dpad_keys in dplut.get(&(current, next)).unwrap() {
-            best = best.min(find_best_dpad_sequence(cache, dplut, dpad_keys, n - 1));
+        for moves in &self.moves[..self.layers.len() - 1] {
+            keys = "A".chars()
+                .chain(keys.chars())
+                .tuple_windows()
+                .map(|(from, to): (char, char)| moves[&(from, to)].as_str())
+                .collect();
         }
 
-        count += best;
-        current = next;
+        Ok(keys)
     }
 
-    cache.insert((dpad_keys.clone(), n), count);
+    /// Feeds `presses` into the last keypad and returns what gets typed on the first one.
+    #[allow(dead_code)]
+    fn simulate(&self, presses: &str) -> Result<String> {
+        let mut keys = presses.to_string();
 
-    count
-}
+        for (layer, keypad) in self.layers.iter().enumerate().rev().skip(1) {
+            let mut arm = keypad.position('A')?;
+            let mut typed = String::new();
 
-fn solve(input: &Input, num_robots: usize) -> usize {
-    let nplut = generate_numpad_lut();
-    let dplut = generate_dpad_lut();
+            for key in keys.chars() {
+                if key == 'A' {
+                    typed.push(keypad.cells[&arm]);
+                    continue;
+                }
 
-    let mut sum = 0;
-    let mut cache = HashMap::new();
+                arm = arm.step(Direction::try_from(key)?);
 
-    for (_, numpad_keys, numerical_part) in input {
-        let mut count = 0;
-        let mut current = NumPadKey::A;
-        for &next in numpad_keys {
-            let mut best = usize::MAX;
-
-            for dpad_keys in nplut.get(&(current, next)).unwrap() {
-                best = best.min(find_best_dpad_sequence(&mut cache, &dplut, dpad_keys, num_robots));
+                if !keypad.cells.contains_key(&arm) {
+                    bail!("Robot arm of keypad {layer} moved off the keys to {arm:?}");
+                }
             }
 
-            count += best;
-            current = next;
+            keys = typed;
         }
 
-        sum += count * numerical_part;
+        Ok(keys)
     }
+}
+
+/// Cost of typing `keys` on a keypad whose arm starts at `A`, given the cost of every move.
+fn sequence_cost(costs: &HashMap<(char, char), usize>, keys: &str) -> usize {
+    "A".chars()
+        .chain(keys.chars())
+        .tuple_windows()
+        .map(|(from, to): (char, char)| costs[&(from, to)])
+        .sum()
+}
 
-    sum
+type Input = Vec<(String, usize)>;
+
+#[aoc_generator(day21)]
+fn parse(input: &str) -> Result<Input> {
+    input
+        .lines()
+        .map(|line| Ok((
+            line.to_string(),
+            line.strip_suffix('A').context(format!("Code does not end in A: {line}"))?.parse::<usize>()?,
+        )))
+        .collect()
+}
+
+fn solve(input: &Input, num_robots: usize) -> Result<usize> {
+    let chain = KeypadChain::door(num_robots)?;
+
+    input
+        .iter()
+        .map(|(code, numerical_part)| Ok(chain.shortest_length(code)? * numerical_part))
+        .sum()
 }
 
 #[aoc(day21, part1)]
-fn part1(input: &Input) -> usize {
+fn part1(input: &Input) -> Result<usize> {
     solve(input, 2)
 }
 
 #[aoc(day21, part2)]
-fn part2(input: &Input) -> usize {
+fn part2(input: &Input) -> Result<usize> {
     solve(input, 25)
 }
 
@@ -267,17 +301,102 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(126384, part1(&parse(EXAMPLE1).unwrap()));
+        assert_eq!(126384, part1(&parse(EXAMPLE1).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn keypad_paths_avoid_gap() {
+        let numeric = Keypad::parse(NUMERIC_KEYPAD).unwrap();
+
+        assert_eq!(vec!["<^<A", "^<<A"], numeric.paths('A', '1').unwrap().into_iter().sorted().collect_vec());
+        assert_eq!(9, numeric.paths('7', 'A').unwrap().len());
+        assert_eq!(vec!["A"], numeric.paths('5', '5').unwrap());
+    }
+
+    #[test]
+    fn shortest_sequence_example1() {
+        let sequence = KeypadChain::door(0).unwrap().shortest_sequence("029A").unwrap();
+        assert!(["<A^A>^^AvvvA", "<A^A^>^AvvvA", "<A^A^^>AvvvA"].contains(&sequence.as_str()));
+        assert_eq!(28, KeypadChain::door(1).unwrap().shortest_sequence("029A").unwrap().len());
+
+        let chain = KeypadChain::door(2).unwrap();
+        for (code, length) in [("029A", 68), ("980A", 60), ("179A", 68), ("456A", 64), ("379A", 64)] {
+            assert_eq!(length, chain.shortest_length(code).unwrap());
+            assert_eq!(length, chain.shortest_sequence(code).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn custom_keypad_chain() {
+        let pad = Keypad::parse(indoc! {"
+            1 2
+            34A
+        "}).unwrap();
+
+        assert_eq!(vec![">^A"], pad.paths('4', '2').unwrap());
+        assert_eq!(vec!["v>>^A"], pad.paths('1', '2').unwrap());
+
+        let directional = Keypad::parse(DIRECTIONAL_KEYPAD).unwrap();
+        let chain = KeypadChain::new(vec![pad, directional]).unwrap();
+
+        assert_eq!("<<A", chain.shortest_sequence("3").unwrap());
+        assert_eq!(4, chain.shortest_length("1").unwrap());
+    }
+
+    #[test]
+    fn simulate_example1() {
+        let chain = KeypadChain::door(2).unwrap();
+
+        assert_eq!("029A", chain.simulate("<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A").unwrap());
+
+        for code in ["029A", "980A", "179A", "456A", "379A"] {
+            assert_eq!(code, chain.simulate(&chain.shortest_sequence(code).unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn simulate_off_the_keys() {
+        let error = KeypadChain::door(0).unwrap().simulate("<<A").unwrap_err();
+
+        assert_eq!("Robot arm of keypad 0 moved off the keys to Position(3, 0)", error.to_string());
+    }
+
+    #[test]
+    fn layer_lengths_example1() {
+        assert_eq!(vec![4, 12, 28, 68], KeypadChain::door(2).unwrap().layer_lengths("029A").unwrap());
+
+        let chain = KeypadChain::door(25).unwrap();
+        let lengths = chain.layer_lengths("029A").unwrap();
+
+        assert_eq!(27, lengths.len());
+        assert_eq!(chain.shortest_length("029A").unwrap(), lengths[26]);
+        assert_eq!(format!("Sequence too long to build: {} presses", lengths[26]), chain.shortest_sequence("029A").unwrap_err().to_string());
+    }
+
+    #[test]
+    fn invalid_keypads() {
+        assert_eq!("Duplicate key in keypad layout: 1", Keypad::parse("11").unwrap_err().to_string());
+
+        let numeric = Keypad::parse(NUMERIC_KEYPAD).unwrap();
+        let error = KeypadChain::new(vec![numeric.clone(), numeric]).err().unwrap();
+        assert_eq!("Controlling keypad is missing key: ^", error.to_string());
+
+        let error = KeypadChain::door(2).unwrap().shortest_length("02B").unwrap_err();
+        assert_eq!("Key not on keypad: B", error.to_string());
     }
 
     #[test]
     fn part1_input() {
-        assert_eq!(156714, part1(&parse(include_str!("../input/2024/day21.txt")).unwrap()));
+        assert_eq!(156714, part1(&parse(include_str!("../input/2024/day21.txt")).unwrap()).unwrap());
     }
 
+    #[test]
+    fn part2_example1() {
+        assert_eq!(154115708116294, part2(&parse(EXAMPLE1).unwrap()).unwrap());
+    }
 
     #[test]
     fn part2_input() {
-        assert_eq!(191139369248202, part2(&parse(include_str!("../input/2024/day21.txt")).unwrap()));
+        assert_eq!(191139369248202, part2(&parse(include_str!("../input/2024/day21.txt")).unwrap()).unwrap());
     }
 }
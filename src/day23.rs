@@ -1,96 +1,33 @@
-use std::collections::{HashMap, VecDeque};
-
 use anyhow::{Context, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
-use hashbrown::HashSet;
 use itertools::Itertools;
 
-type Input = HashMap<String, HashSet<String>>;
+use crate::utils::graph::UndirectedGraph;
+
+type Input = UndirectedGraph;
 
 #[aoc_generator(day23)]
 fn parse(input: &str) -> Result<Input> {
     input
         .lines()
-        .map(|line| {
-            let (a, b) = line.split_once("-").with_context(|| format!("Unable to parse line: {line}"))?;
-            Ok([
-                (a.to_string(), b.to_string()),
-                (b.to_string(), a.to_string()),
-            ])
-        })
-        .flatten_ok()
-        .process_results(|connections| connections.into_grouping_map().collect::<HashSet<_>>())
-
+        .map(|line| line.split_once("-").with_context(|| format!("Unable to parse line: {line}")))
+        .process_results(|connections| connections.collect())
 }
 
 #[aoc(day23, part1)]
-fn part1(connections: &Input) -> usize {
-    let computers_sorted = connections.iter().sorted_by_key(|(computer, _)| computer.as_str()).collect_vec();
-    let mut groups_of_three = vec![];
-
-    for (a, ac) in computers_sorted {
-        for b in ac {
-            if b <= a { continue; }
-
-            for c in ac.intersection(&connections[b]) {
-                if c <= b { continue; }
-                groups_of_three.push([a, b, c]);
-            }
-        }
-    }
-
-    groups_of_three
+fn part1(network: &Input) -> usize {
+    network
+        .k_cliques(3)
         .into_iter()
-        .filter(|group| group.iter().any(|computer| computer.starts_with("t")))
+        .filter(|group| network.names(group).any(|computer| computer.starts_with("t")))
         .count()
 }
 
 #[aoc(day23, part2)]
-fn part2(connections: &Input) -> Option<String> {
-    let computers = connections.keys().map(String::as_str).sorted().collect_vec();
-    let computer_indices = computers.iter().enumerate().map(|(i, n)| (*n, i)).collect::<HashMap<_,_>>();
-    let connections = computers
-        .iter()
-        .map(|&computer| {
-            connections[computer]
-                .iter()
-                .map(String::as_str)
-                .map(|connection| computer_indices[connection])
-                .collect_vec()
-        })
-        .collect_vec();
-
-    let mut best_clique = None;
-    let mut best_clique_len = 0;
-    let mut queue = (0..computers.len()).map(|i| (vec![i], connections[i].clone())).collect::<VecDeque<_>>();
-
-    while let Some((clique, candidates)) = queue.pop_front() {
-        if clique.len() + candidates.len() <= best_clique_len { continue; }
-        if candidates.is_empty() { continue; }
+fn part2(network: &Input) -> Option<String> {
+    let clique = network.maximum_clique()?;
 
-        let candidate = candidates[0];
-
-        if clique.iter().all(|computer| connections[*computer].contains(&candidate)) {
-            let mut candidate_clique = clique.clone();
-            candidate_clique.push(candidate);
-            candidate_clique.sort();
-
-            if candidate_clique.len() > best_clique_len {
-                best_clique = Some(candidate_clique.clone());
-                best_clique_len = candidate_clique.len();
-            }
-
-            if candidates.len() > 1 {
-                queue.push_back((candidate_clique, candidates[1..].to_vec()));
-            }
-        }
-
-        if candidates.len() > 1 {
-            queue.push_back((clique, candidates[1..].to_vec()));
-        }
-    }
-
-    best_clique.map(|clique| clique.into_iter().map(|i| computers[i]).join(","))
+    Some(network.names(&clique).sorted().join(","))
 }
 
 #[cfg(test)]
@@ -157,4 +94,18 @@ mod tests {
         assert_ne!(None, ans);
         assert_eq!("bg,bu,ce,ga,hw,jw,nf,nt,ox,tj,uu,vk,wp", ans.unwrap());
     }
+
+    #[test]
+    fn cliques_example1() {
+        let network = parse(EXAMPLE1).unwrap();
+
+        assert_eq!(12, network.k_cliques(3).len());
+        assert_eq!(1, network.k_cliques(4).len());
+        assert!(network.k_cliques(5).is_empty());
+        assert!(network.maximal_cliques().iter().all(|clique| clique.iter().tuple_combinations().all(|(a, b)| network.contains_edge(*a, *b))));
+        assert_eq!(
+            vec!["co,de,ka,ta"],
+            network.maximal_cliques().iter().filter(|clique| clique.len() == 4).map(|clique| network.names(clique).sorted().join(",")).collect_vec(),
+        );
+    }
 }
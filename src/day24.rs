@@ -1,9 +1,13 @@
 use std::str::FromStr;
 
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::HashMap;
-use itertools::Itertools;
+use itertools::{chain, iproduct, Itertools};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::utils::graph::{Cycle, Graph};
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 enum Wire {
@@ -50,108 +54,522 @@ fn parse(input: &str) -> Result<Input> {
         .collect()
 }
 
-fn resolve_wire(wires: &Wires, wire_name: &str) -> Result<bool> {
-    fn resolve_wire_impl(wire_values: &mut HashMap<String, bool>, wires: &Wires, wire_name: &str) -> Result<bool> {
-        if let Some(value) = wire_values.get(wire_name) {
-            return Ok(*value);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Gate {
+    Constant(bool),
+    And(usize, usize),
+    Or(usize, usize),
+    Xor(usize, usize),
+}
+
+/// The netlist compiled into a flat gate list. Wires are numbered in topological order, so gate
+/// `i` only ever reads wires with a lower index and a single forward pass evaluates everything.
+#[derive(Clone, Debug)]
+struct Circuit {
+    names: Vec<String>,
+    gates: Vec<Gate>,
+}
+
+impl Circuit {
+    fn compile(wires: &Wires) -> Result<Circuit> {
+        let mut graph = Graph::new();
+
+        for (wire_name, wire) in wires.iter().sorted_by_key(|(wire_name, _)| *wire_name) {
+            graph.add_node(wire_name.as_str());
+
+            for input in inputs(wire) {
+                if !wires.contains_key(input) {
+                    bail!("Unknown wire: {input}");
+                }
+
+                graph.add_edge(input, wire_name.as_str());
+            }
         }
 
-        let signal = wires.get(wire_name).with_context(|| format!("Unknown wire: {wire_name}"))?;
+        let order = graph
+            .topological_sort()
+            .map_err(|Cycle(cycle)| anyhow!("Combinational loop: {}", cycle.join(" -> ")))?;
+
+        let indices = order
+            .iter()
+            .enumerate()
+            .map(|(index, wire_name)| (wire_name.to_string(), index))
+            .collect::<HashMap<_, _>>();
+
+        let gates = order
+            .iter()
+            .map(|wire_name| match &wires[*wire_name] {
+                Wire::Constant(value) => Gate::Constant(*value),
+                Wire::And(a, b) => Gate::And(indices[a], indices[b]),
+                Wire::Or(a, b) => Gate::Or(indices[a], indices[b]),
+                Wire::Xor(a, b) => Gate::Xor(indices[a], indices[b]),
+            })
+            .collect();
+
+        Ok(Circuit {
+            names: order.into_iter().map(str::to_string).collect(),
+            gates,
+        })
+    }
 
-        let value = match signal {
-            Wire::Constant(value) => *value,
-            Wire::And(a, b) => {
-                let a = resolve_wire_impl(wire_values, wires, a)?;
-                let b = resolve_wire_impl(wire_values, wires, b)?;
+    /// Wire indices of `prefix00`, `prefix01`, ..., ordered from the least significant bit.
+    fn bus(&self, prefix: &str) -> Vec<usize> {
+        self.names
+            .iter()
+            .enumerate()
+            .filter_map(|(index, wire_name)| Some((bus_bit(wire_name, prefix)?, index)))
+            .sorted()
+            .map(|(_, index)| index)
+            .collect()
+    }
 
-                a && b
-            },
-            Wire::Or(a, b) => {
-                let a = resolve_wire_impl(wire_values, wires, a)?;
-                let b = resolve_wire_impl(wire_values, wires, b)?;
+    /// Evaluates 64 copies of the circuit at once. Bit `k` of every value belongs to copy `k`, and
+    /// `overrides` replaces the value of any wire, typically the `x` and `y` inputs.
+    fn evaluate(&self, overrides: &[(usize, u64)]) -> Vec<u64> {
+        let mut overridden = vec![None; self.gates.len()];
 
-                a || b
-            },
-            Wire::Xor(a, b) => {
-                let a = resolve_wire_impl(wire_values, wires, a)?;
-                let b = resolve_wire_impl(wire_values, wires, b)?;
+        for &(index, value) in overrides {
+            overridden[index] = Some(value);
+        }
 
-                (a && !b) || (!a && b)
-            },
-        };
+        let mut values = vec![0; self.gates.len()];
+
+        for (index, gate) in self.gates.iter().enumerate() {
+            values[index] = overridden[index].unwrap_or_else(|| match *gate {
+                Gate::Constant(value) => if value { u64::MAX } else { 0 },
+                Gate::And(a, b) => values[a] & values[b],
+                Gate::Or(a, b) => values[a] | values[b],
+                Gate::Xor(a, b) => values[a] ^ values[b],
+            });
+        }
 
-        wire_values.insert(wire_name.to_string(), value);
+        values
+    }
 
-        Ok(value)
+    /// Spreads up to 64 bus values, given as little-endian bits, over the lanes of the bus wires,
+    /// ready for `evaluate`.
+    fn bus_lanes(&self, prefix: &str, values: &[Vec<bool>]) -> Vec<(usize, u64)> {
+        self.bus(prefix)
+            .into_iter()
+            .enumerate()
+            .map(|(bit, index)| {
+                let lanes = values
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (lane, value)| acc | ((value.get(bit) == Some(&true)) as u64) << lane);
+
+                (index, lanes)
+            })
+            .collect()
     }
 
-    resolve_wire_impl(&mut HashMap::new(), wires, wire_name)
+    fn read_bus(&self, values: &[u64], prefix: &str, lanes: usize) -> Vec<Vec<bool>> {
+        let bus = self.bus(prefix);
+
+        (0..lanes)
+            .map(|lane| bus.iter().map(|&index| (values[index] >> lane) & 1 == 1).collect())
+            .collect()
+    }
 }
 
-fn get_signal(wires: &Wires, signal_name: &str) -> Result<u64> {
-    let num_wires = wires.keys().filter(|wire_name| wire_name.starts_with(signal_name)).count();
+fn bus_bit(wire_name: &str, prefix: &str) -> Option<usize> {
+    let bit = wire_name.strip_prefix(prefix)?;
 
-    (0..num_wires)
-        .rev()
-        .map(|wire_number| format!("{signal_name}{wire_number:02}"))
-        .map(|wire_name| resolve_wire(wires, &wire_name))
-        .process_results(|wire_values| {
-            wire_values.fold(0, |acc, v| (acc << 1) + v as u64)
-        })
+    if bit.is_empty() || !bit.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    bit.parse().ok()
 }
 
-#[allow(dead_code)]
-fn set_signal(wires: &mut Wires, signal_name: &str, value: u64) {
-    assert!(value < (1 << 45));
+/// Number of `prefixNN` wires, checking that they form a contiguous bus starting at bit 0.
+fn bus_width(wires: &Wires, prefix: &str) -> Result<usize> {
+    let bits = wires
+        .keys()
+        .filter_map(|wire_name| bus_bit(wire_name, prefix))
+        .sorted()
+        .collect_vec();
+
+    if let Some(missing) = bits.iter().enumerate().find(|(expected, bit)| expected != *bit).map(|(expected, _)| expected) {
+        bail!("Bus {prefix} is missing wire {prefix}{missing:02}");
+    }
+
+    Ok(bits.len())
+}
+
+/// A value that can be put on, or read from, a bus of wires as little-endian bits.
+trait BusValue: Sized {
+    fn from_bits(bits: &[bool]) -> Result<Self>;
+    fn to_bits(&self, width: usize) -> Result<Vec<bool>>;
+}
+
+macro_rules! impl_bus_value {
+    ($($t:ty),*) => {$(
+        impl BusValue for $t {
+            fn from_bits(bits: &[bool]) -> Result<Self> {
+                if bits.iter().skip(<$t>::BITS as usize).any(|bit| *bit) {
+                    bail!("Signal does not fit in {}", stringify!($t));
+                }
+
+                Ok(bits.iter().take(<$t>::BITS as usize).rev().fold(0, |acc, bit| (acc << 1) | *bit as $t))
+            }
 
-    let mut value = value;
+            fn to_bits(&self, width: usize) -> Result<Vec<bool>> {
+                if width < <$t>::BITS as usize && self >> width != 0 {
+                    bail!("Value {self} does not fit in {width} bits");
+                }
 
-    for i in 0..45 {
-        wires.insert(format!("{signal_name}{i:02}"), Wire::Constant((value % 2) == 1));
-        value >>= 1;
+                Ok((0..width).map(|bit| bit < <$t>::BITS as usize && (self >> bit) & 1 == 1).collect())
+            }
+        }
+    )*};
+}
+
+impl_bus_value!(u64, u128);
+
+impl BusValue for BigUint {
+    fn from_bits(bits: &[bool]) -> Result<Self> {
+        let mut value = BigUint::zero();
+
+        for (bit, set) in bits.iter().enumerate() {
+            value.set_bit(bit as u64, *set);
+        }
+
+        Ok(value)
     }
+
+    fn to_bits(&self, width: usize) -> Result<Vec<bool>> {
+        if self.bits() > width as u64 {
+            bail!("Value {self} does not fit in {width} bits");
+        }
+
+        Ok((0..width).map(|bit| self.bit(bit as u64)).collect())
+    }
+}
+
+fn get_signal<T: BusValue>(wires: &Wires, signal_name: &str) -> Result<T> {
+    bus_width(wires, signal_name)?;
+
+    let circuit = Circuit::compile(wires)?;
+    let values = circuit.evaluate(&[]);
+
+    T::from_bits(&circuit.read_bus(&values, signal_name, 1)[0])
 }
 
 #[allow(dead_code)]
-fn swap_wires(wires: &Wires) -> Wires {
-    let mut wires = wires.clone();
+fn set_signal<T: BusValue>(wires: &mut Wires, signal_name: &str, value: &T) -> Result<()> {
+    let width = bus_width(wires, signal_name)?;
 
-    let z17 = wires["z17"].clone();
-    let cmv = wires["cmv"].clone();
-    wires.insert("cmv".to_string(), z17);
-    wires.insert("z17".to_string(), cmv);
+    for (i, bit) in value.to_bits(width)?.into_iter().enumerate() {
+        wires.insert(format!("{signal_name}{i:02}"), Wire::Constant(bit));
+    }
 
-    let z23 = wires["z23"].clone();
-    let rmj = wires["rmj"].clone();
-    wires.insert("rmj".to_string(), z23);
-    wires.insert("z23".to_string(), rmj);
+    Ok(())
+}
 
-    let z30 = wires["z30"].clone();
-    let rdg = wires["rdg"].clone();
-    wires.insert("rdg".to_string(), z30);
-    wires.insert("z30".to_string(), rdg);
+fn swap_wires(wires: &Wires, swaps: &[(String, String)]) -> Wires {
+    let mut wires = wires.clone();
 
-    let mwp = wires["mwp"].clone();
-    let btb = wires["btb"].clone();
-    wires.insert("btb".to_string(), mwp);
-    wires.insert("mwp".to_string(), btb);
+    for (a, b) in swaps {
+        let wire_a = wires[a].clone();
+        let wire_b = wires[b].clone();
+        wires.insert(a.to_string(), wire_b);
+        wires.insert(b.to_string(), wire_a);
+    }
 
     wires
 }
 
+fn is_input(wire_name: &str) -> bool {
+    bus_bit(wire_name, "x").is_some() || bus_bit(wire_name, "y").is_some()
+}
+
+fn is_first_bit(a: &str, b: &str) -> bool {
+    [a, b].into_iter().all(|wire_name| [bus_bit(wire_name, "x"), bus_bit(wire_name, "y")].contains(&Some(0)))
+}
+
+/// Checks every gate against the shape of a ripple-carry adder built from full adders, and returns
+/// the outputs of the gates that don't fit:
+///
+/// - Every `z` wire is driven by a XOR, except the last one which is the final carry (an OR).
+/// - A XOR that doesn't read `x`/`y` inputs computes a sum bit, so it must drive a `z` wire.
+/// - A XOR of `xNN` and `yNN` (except bit 0) is a half-sum, so it must feed another XOR.
+/// - An AND (except `x00 AND y00`) produces part of a carry, so it must feed an OR.
+fn find_miswired_outputs(wires: &Wires) -> Result<Vec<String>> {
+    let last_output = bus_width(wires, "z")?.checked_sub(1);
+    let mut consumers: HashMap<&str, Vec<&Wire>> = HashMap::new();
+
+    for wire in wires.values() {
+        if let Wire::And(a, b) | Wire::Or(a, b) | Wire::Xor(a, b) = wire {
+            consumers.entry(a).or_default().push(wire);
+            consumers.entry(b).or_default().push(wire);
+        }
+    }
+
+    let feeds = |wire_name: &str, is_kind: fn(&Wire) -> bool| {
+        consumers.get(wire_name).is_some_and(|wires| wires.iter().any(|wire| is_kind(wire)))
+    };
+
+    let miswired = wires
+        .iter()
+        .filter(|(wire_name, wire)| {
+            let output_bit = bus_bit(wire_name, "z");
+            let is_output = output_bit.is_some();
+            let is_last_output = output_bit.is_some() && output_bit == last_output;
+
+            match wire {
+                Wire::Constant(_) => false,
+                _ if is_last_output => !matches!(wire, Wire::Or(_, _)),
+                Wire::Xor(a, b) if is_input(a) && is_input(b) => {
+                    !is_first_bit(a, b) && !feeds(wire_name, |wire| matches!(wire, Wire::Xor(_, _)))
+                },
+                Wire::Xor(_, _) => !is_output,
+                Wire::And(a, b) => {
+                    is_output || (!is_first_bit(a, b) && !feeds(wire_name, |wire| matches!(wire, Wire::Or(_, _))))
+                },
+                Wire::Or(_, _) => is_output,
+            }
+        })
+        .map(|(wire_name, _)| wire_name.to_string())
+        .sorted()
+        .collect();
+
+    Ok(miswired)
+}
+
+fn pairings(wire_names: &[String]) -> Vec<Vec<(String, String)>> {
+    let Some((first, rest)) = wire_names.split_first() else {
+        return vec![vec![]];
+    };
+
+    (0..rest.len())
+        .flat_map(|i| {
+            let partner = &rest[i];
+            let remaining = rest.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, w)| w.clone()).collect_vec();
+
+            pairings(&remaining).into_iter().map(move |mut pairing| {
+                pairing.insert(0, (first.clone(), partner.clone()));
+                pairing
+            })
+        })
+        .collect()
+}
+
+/// Test vectors for checking an adder of the given width. For every bit, all combinations of the
+/// two input bits and the incoming carry are tried, plus a few vectors rippling a carry all the
+/// way through.
+fn adder_test_vectors(width: usize) -> Vec<(BigUint, BigUint)> {
+    let bit = |i: usize| BigUint::one() << i;
+    let mask = (BigUint::one() << width) - 1u32;
+
+    (0..width)
+        .flat_map(|i| {
+            let lower = if i == 0 { vec![BigUint::zero()] } else { vec![BigUint::zero(), bit(i - 1)] };
+
+            iproduct!([BigUint::zero(), bit(i)], [BigUint::zero(), bit(i)], lower.clone(), lower)
+                .map(|(x, y, x_lower, y_lower)| (x + x_lower, y + y_lower))
+        })
+        .chain([(mask.clone(), BigUint::one()), (mask.clone(), mask)])
+        .collect()
+}
+
+/// Simulates the netlist as an adder and returns, in ascending order, the bits that are the lowest
+/// wrong `z` bit for at least one of the `adder_test_vectors`. Taking only the lowest bit keeps a
+/// broken carry from also flagging every bit above it.
+fn faulty_bits(wires: &Wires) -> Result<Vec<usize>> {
+    let width = bus_width(wires, "x")?;
+
+    if bus_width(wires, "y")? != width {
+        bail!("Buses x and y have different widths");
+    }
+
+    if bus_width(wires, "z")? != width + 1 {
+        bail!("Bus z should be one bit wider than x and y");
+    }
+
+    let circuit = Circuit::compile(wires)?;
+    let mut faulty = vec![false; width + 1];
+
+    for test_vectors in adder_test_vectors(width).chunks(64) {
+        let xs = test_vectors.iter().map(|(x, _)| x.to_bits(width)).collect::<Result<Vec<_>>>()?;
+        let ys = test_vectors.iter().map(|(_, y)| y.to_bits(width)).collect::<Result<Vec<_>>>()?;
+        let overrides = chain!(circuit.bus_lanes("x", &xs), circuit.bus_lanes("y", &ys)).collect_vec();
+        let values = circuit.evaluate(&overrides);
+        let zs = circuit.read_bus(&values, "z", test_vectors.len());
+
+        for ((x, y), z) in test_vectors.iter().zip(zs) {
+            let expected = (x + y).to_bits(width + 1)?;
+
+            if let Some(bit) = expected.into_iter().zip(z).position(|(expected, actual)| expected != actual) {
+                faulty[bit] = true;
+            }
+        }
+    }
+
+    Ok(faulty.into_iter().positions(|faulty| faulty).collect())
+}
+
+/// Finds the swaps that turn the netlist into a working adder. The structural check narrows the
+/// candidates down to the miswired outputs, and each way of pairing them up is then simulated.
+fn find_swaps(wires: &Wires) -> Result<Vec<(String, String)>> {
+    let miswired = find_miswired_outputs(wires)?;
+
+    if !miswired.len().is_multiple_of(2) || miswired.len() > 10 {
+        bail!("Expected a small even number of miswired outputs, found {}: {}", miswired.len(), miswired.join(","));
+    }
+
+    pairings(&miswired)
+        .into_iter()
+        .find(|swaps| faulty_bits(&swap_wires(wires, swaps)).is_ok_and(|faulty| faulty.is_empty()))
+        .with_context(|| format!("No pairing of the miswired outputs {} produces a working adder", miswired.join(",")))
+}
+
+fn inputs(wire: &Wire) -> Vec<&str> {
+    match wire {
+        Wire::Constant(_) => vec![],
+        Wire::And(a, b) | Wire::Or(a, b) | Wire::Xor(a, b) => vec![a.as_str(), b.as_str()],
+    }
+}
+
+fn bit_index(wire_name: &str) -> Option<usize> {
+    ["x", "y", "z"].into_iter().find_map(|prefix| bus_bit(wire_name, prefix))
+}
+
+/// Assigns each wire to the highest bit index it depends on, which puts the gates of each full
+/// adder together with the `xNN`, `yNN` and `zNN` wires of that bit.
+fn bit_clusters(wires: &Wires) -> HashMap<&str, usize> {
+    fn bit_cluster<'a>(clusters: &mut HashMap<&'a str, Option<usize>>, wires: &'a Wires, wire_name: &'a str) -> Option<usize> {
+        if let Some(cluster) = clusters.get(wire_name) {
+            return *cluster;
+        }
+
+        clusters.insert(wire_name, None);
+
+        let cluster = match (bit_index(wire_name), wires.get(wire_name)) {
+            (Some(index), _) => Some(index),
+            (None, Some(wire)) => inputs(wire)
+                .into_iter()
+                .filter_map(|input| bit_cluster(clusters, wires, input))
+                .max(),
+            (None, None) => None,
+        };
+
+        clusters.insert(wire_name, cluster);
+        cluster
+    }
+
+    let mut clusters = HashMap::new();
+
+    for wire_name in wires.keys() {
+        bit_cluster(&mut clusters, wires, wire_name);
+    }
+
+    clusters
+        .into_iter()
+        .filter_map(|(wire_name, cluster)| Some((wire_name, cluster?)))
+        .collect()
+}
+
+fn gate_name(wire: &Wire) -> &'static str {
+    match wire {
+        Wire::Constant(_) => "CONST",
+        Wire::And(_, _) => "AND",
+        Wire::Or(_, _) => "OR",
+        Wire::Xor(_, _) => "XOR",
+    }
+}
+
+/// Renders the netlist as a Graphviz digraph with one node per wire, optionally grouping the wires
+/// into one cluster per bit of the adder.
+#[allow(dead_code)]
+fn to_dot(wires: &Wires, cluster_by_bit: bool) -> String {
+    let node = |wire_name: &str| match &wires[wire_name] {
+        Wire::Constant(value) => format!("{wire_name} [label=\"{wire_name} = {}\", shape=circle];", *value as u8),
+        wire => format!("{wire_name} [label=\"{wire_name}\\n{}\", shape=box];", gate_name(wire)),
+    };
+
+    let mut lines = vec!["digraph day24 {".to_string(), "    rankdir=LR;".to_string()];
+    let clusters = if cluster_by_bit { bit_clusters(wires) } else { HashMap::new() };
+    let grouped = wires
+        .keys()
+        .map(String::as_str)
+        .sorted()
+        .into_group_map_by(|wire_name| clusters.get(wire_name).copied());
+
+    for (cluster, wire_names) in grouped.into_iter().sorted() {
+        match cluster {
+            Some(index) => {
+                lines.push(format!("    subgraph cluster_bit{index:02} {{"));
+                lines.push(format!("        label=\"bit {index:02}\";"));
+                lines.extend(wire_names.into_iter().map(|wire_name| format!("        {}", node(wire_name))));
+                lines.push("    }".to_string());
+            },
+            None => {
+                lines.extend(wire_names.into_iter().map(|wire_name| format!("    {}", node(wire_name))));
+            },
+        }
+    }
+
+    for (wire_name, wire) in wires.iter().sorted_by_key(|(wire_name, _)| *wire_name) {
+        lines.extend(inputs(wire).into_iter().map(|input| format!("    {input} -> {wire_name};")));
+    }
+
+    lines.push("}".to_string());
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+/// Renders the netlist as a structural Verilog module. Constant wires become input ports, `z`
+/// wires become output ports, and every gate is instantiated as a primitive.
+#[allow(dead_code)]
+fn to_verilog(wires: &Wires, module_name: &str) -> String {
+    let sorted_wires = wires.iter().sorted_by_key(|(wire_name, _)| *wire_name).collect_vec();
+    let is_port_input = |wire: &Wire| matches!(wire, Wire::Constant(_));
+    let is_port_output = |wire_name: &str, wire: &Wire| wire_name.starts_with('z') && !is_port_input(wire);
+
+    let ports = sorted_wires
+        .iter()
+        .filter(|(_, wire)| is_port_input(wire))
+        .map(|(wire_name, _)| format!("    input wire {wire_name}"))
+        .chain(sorted_wires
+            .iter()
+            .filter(|(wire_name, wire)| is_port_output(wire_name, wire))
+            .map(|(wire_name, _)| format!("    output wire {wire_name}"))
+        )
+        .join(",\n");
+
+    let mut lines = vec![format!("module {module_name} (\n{ports}\n);")];
+
+    lines.extend(sorted_wires
+        .iter()
+        .filter(|(wire_name, wire)| !is_port_input(wire) && !is_port_output(wire_name, wire))
+        .map(|(wire_name, _)| format!("    wire {wire_name};"))
+    );
+
+    lines.push(String::new());
+
+    lines.extend(sorted_wires
+        .iter()
+        .filter(|(_, wire)| !is_port_input(wire))
+        .map(|(wire_name, wire)| {
+            let inputs = inputs(wire).join(", ");
+            format!("    {} g_{wire_name} ({wire_name}, {inputs});", gate_name(wire).to_lowercase())
+        })
+    );
+
+    lines.push("endmodule".to_string());
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
 #[aoc(day24, part1)]
 fn part1(wires: &Input) -> Result<u64> {
     get_signal(wires, "z")
 }
 
 #[aoc(day24, part2)]
-fn part2(_: &Input) -> String {
-    // Swap "tfc OR  qhq -> z17" with "wvj XOR qwg -> cmv"
-    // Swap "kkf AND pbw -> z23" with "kkf XOR pbw -> rmj"
-    // Swap "x30 AND y30 -> z30" with "knj XOR rvp -> rdg"
-    // Swap "y38 AND x38 -> mwp" with "y38 XOR x38 -> btb"
+fn part2(wires: &Input) -> Result<String> {
+    let swaps = find_swaps(wires)?;
 
-    "btb,cmv,mwp,rdg,rmj,z17,z23,z30".to_string()
+    Ok(swaps.into_iter().flat_map(|(a, b)| [a, b]).sorted().join(","))
 }
 
 #[cfg(test)]
@@ -240,46 +658,261 @@ mod tests {
 
     #[test]
     fn part2_input_verification1() {
-        let mut wires = swap_wires(&parse(include_str!("../input/2024/day24.txt")).unwrap());
+        let wires = parse(include_str!("../input/2024/day24.txt")).unwrap();
+        let mut wires = swap_wires(&wires, &find_swaps(&wires).unwrap());
         let x = 0b_00001010_10101010_10101010_10101010_10101010_10101010_u64;
         let y = 0b_00010101_01010101_01010101_01010101_01010101_01010101_u64;
-        set_signal(&mut wires, "x", x);
-        set_signal(&mut wires, "y", y);
-        assert_eq!(x + y, get_signal(&wires, "z").unwrap());
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &y).unwrap();
+        assert_eq!(x + y, get_signal::<u64>(&wires, "z").unwrap());
     }
 
     #[test]
     fn part2_input_verification2() {
-        let mut wires = swap_wires(&parse(include_str!("../input/2024/day24.txt")).unwrap());
+        let wires = parse(include_str!("../input/2024/day24.txt")).unwrap();
+        let mut wires = swap_wires(&wires, &find_swaps(&wires).unwrap());
         let x = 0b_00011111_11111111_11111111_11111111_11111111_11111111_u64;
         let y = 0b_00011111_11111111_11111111_11111111_11111111_11111111_u64;
-        set_signal(&mut wires, "x", x);
-        set_signal(&mut wires, "y", y);
-        assert_eq!(x + y, get_signal(&wires, "z").unwrap());
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &y).unwrap();
+        assert_eq!(x + y, get_signal::<u64>(&wires, "z").unwrap());
     }
 
     #[test]
     fn part2_input_verification3() {
-        let mut wires = swap_wires(&parse(include_str!("../input/2024/day24.txt")).unwrap());
+        let wires = parse(include_str!("../input/2024/day24.txt")).unwrap();
+        let mut wires = swap_wires(&wires, &find_swaps(&wires).unwrap());
         let x = 0b_00011111_11111111_11111111_11111111_11111111_11111111_u64;
         let y = 0b_00010000_00000000_00000000_00000000_00000000_00000000_u64;
-        set_signal(&mut wires, "x", x);
-        set_signal(&mut wires, "y", y);
-        assert_eq!(x + y, get_signal(&wires, "z").unwrap());
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &y).unwrap();
+        assert_eq!(x + y, get_signal::<u64>(&wires, "z").unwrap());
     }
 
     #[test]
     fn part2_input_verification4() {
-        let mut wires = swap_wires(&parse(include_str!("../input/2024/day24.txt")).unwrap());
+        let wires = parse(include_str!("../input/2024/day24.txt")).unwrap();
+        let mut wires = swap_wires(&wires, &find_swaps(&wires).unwrap());
         let x = 0b_00011111_11111111_11111111_11111111_11111111_11111111_u64;
         let y = 0b_00000000_00000000_00000000_00000000_00000000_00000001_u64;
-        set_signal(&mut wires, "x", x);
-        set_signal(&mut wires, "y", y);
-        assert_eq!(x + y, get_signal(&wires, "z").unwrap());
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &y).unwrap();
+        assert_eq!(x + y, get_signal::<u64>(&wires, "z").unwrap());
     }
 
     #[test]
     fn part2_input() {
-        assert_eq!("btb,cmv,mwp,rdg,rmj,z17,z23,z30", part2(&parse(include_str!("../input/2024/day24.txt")).unwrap()));
+        assert_eq!("btb,cmv,mwp,rdg,rmj,z17,z23,z30", part2(&parse(include_str!("../input/2024/day24.txt")).unwrap()).unwrap());
+    }
+
+    fn ripple_carry_adder(bits: usize, swaps: &[(&str, &str)]) -> String {
+        let mut gates = vec![
+            ("x00 XOR y00".to_string(), "z00".to_string()),
+            ("x00 AND y00".to_string(), "c00".to_string()),
+        ];
+
+        for i in 1..bits {
+            let carry_out = if i == bits - 1 { format!("z{bits:02}") } else { format!("c{i:02}") };
+            gates.push((format!("x{i:02} XOR y{i:02}"), format!("s{i:02}")));
+            gates.push((format!("y{i:02} AND x{i:02}"), format!("a{i:02}")));
+            gates.push((format!("s{i:02} XOR c{:02}", i - 1), format!("z{i:02}")));
+            gates.push((format!("c{:02} AND s{i:02}", i - 1), format!("h{i:02}")));
+            gates.push((format!("a{i:02} OR h{i:02}"), carry_out));
+        }
+
+        for (_, output) in gates.iter_mut() {
+            for (a, b) in swaps {
+                if output == a {
+                    *output = b.to_string();
+                } else if output == b {
+                    *output = a.to_string();
+                }
+            }
+        }
+
+        let inputs = (0..bits).flat_map(|i| [format!("x{i:02}: {}", i % 2), format!("y{i:02}: {}", i % 3 % 2)]);
+        let gates = gates.into_iter().map(|(gate, output)| format!("{gate} -> {output}"));
+
+        inputs.chain([String::new()]).chain(gates).join("\n")
+    }
+
+    #[test]
+    fn part2_generated_adder() {
+        let input = ripple_carry_adder(45, &[("z17", "c17"), ("z23", "h23"), ("z30", "a30"), ("a38", "s38")]);
+        assert_eq!("a30,a38,c17,h23,s38,z17,z23,z30", part2(&parse(&input).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn part2_generated_adder_without_faults() {
+        let input = ripple_carry_adder(45, &[]);
+        assert_eq!("", part2(&parse(&input).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn part2_generated_adder_with_bus_like_names() {
+        let input = ripple_carry_adder(45, &[("z17", "c17"), ("a38", "s38")]).replace('s', "xor").replace('c', "zap");
+        assert_eq!("a38,xor38,z17,zap17", part2(&parse(&input).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn part2_generated_adder_wider_than_100_bits() {
+        let input = ripple_carry_adder(150, &[]);
+        assert_eq!("", part2(&parse(&input).unwrap()).unwrap());
+
+        let input = ripple_carry_adder(150, &[("z120", "c120"), ("a99", "s99")]);
+        assert_eq!("a99,c120,s99,z120", part2(&parse(&input).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn to_dot_example1() {
+        assert_eq!(indoc! {r#"
+            digraph day24 {
+                rankdir=LR;
+                subgraph cluster_bit00 {
+                    label="bit 00";
+                    x00 [label="x00 = 1", shape=circle];
+                    y00 [label="y00 = 0", shape=circle];
+                    z00 [label="z00\nAND", shape=box];
+                }
+                subgraph cluster_bit01 {
+                    label="bit 01";
+                    x01 [label="x01 = 1", shape=circle];
+                    y01 [label="y01 = 1", shape=circle];
+                    z01 [label="z01\nXOR", shape=box];
+                }
+                subgraph cluster_bit02 {
+                    label="bit 02";
+                    x02 [label="x02 = 1", shape=circle];
+                    y02 [label="y02 = 0", shape=circle];
+                    z02 [label="z02\nOR", shape=box];
+                }
+                x00 -> z00;
+                y00 -> z00;
+                x01 -> z01;
+                y01 -> z01;
+                x02 -> z02;
+                y02 -> z02;
+            }
+        "#}, to_dot(&parse(EXAMPLE1).unwrap(), true));
+    }
+
+    #[test]
+    fn to_dot_clusters_internal_gates() {
+        let dot = to_dot(&parse(&ripple_carry_adder(3, &[])).unwrap(), true);
+        let cluster_bit01 = dot.split("subgraph").nth(2).unwrap();
+
+        assert!(cluster_bit01.starts_with(" cluster_bit01"));
+        assert!(["a01", "c01", "h01", "s01", "x01", "y01", "z01"].iter().all(|wire| cluster_bit01.contains(&format!("{wire} ["))));
+    }
+
+    #[test]
+    fn to_verilog_example1() {
+        assert_eq!(indoc! {"
+            module day24 (
+                input wire x00,
+                input wire x01,
+                input wire x02,
+                input wire y00,
+                input wire y01,
+                input wire y02,
+                output wire z00,
+                output wire z01,
+                output wire z02
+            );
+
+                and g_z00 (z00, x00, y00);
+                xor g_z01 (z01, x01, y01);
+                or g_z02 (z02, x02, y02);
+            endmodule
+        "}, to_verilog(&parse(EXAMPLE1).unwrap(), "day24"));
+    }
+
+    #[test]
+    fn part1_combinational_loop() {
+        let input = indoc! {"
+            x00: 1
+
+            x00 AND abc -> def
+            def OR x00 -> abc
+            abc XOR def -> z00
+        "};
+
+        let error = part1(&parse(input).unwrap()).unwrap_err();
+        assert!(error.to_string().starts_with("Combinational loop: "));
+    }
+
+    #[test]
+    fn circuit_evaluates_lanes_independently() {
+        let circuit = Circuit::compile(&parse(&ripple_carry_adder(45, &[])).unwrap()).unwrap();
+        let xs = (0..64_u64).map(|i| (i * 0x1234_5678_9abc) & ((1 << 45) - 1)).collect_vec();
+        let ys = (0..64_u64).map(|i| (i * 0xfedc_ba98_7654) & ((1 << 45) - 1)).collect_vec();
+        let to_bits = |values: &[u64]| values.iter().map(|value| value.to_bits(45).unwrap()).collect_vec();
+        let overrides = chain!(circuit.bus_lanes("x", &to_bits(&xs)), circuit.bus_lanes("y", &to_bits(&ys))).collect_vec();
+        let values = circuit.evaluate(&overrides);
+
+        assert_eq!(
+            xs.iter().zip(&ys).map(|(x, y)| x + y).collect_vec(),
+            circuit.read_bus(&values, "z", 64).iter().map(|z| u64::from_bits(z).unwrap()).collect_vec(),
+        );
+    }
+
+    #[test]
+    fn signals_wider_than_u64() {
+        let mut wires = parse(&ripple_carry_adder(100, &[])).unwrap();
+        let x = (1_u128 << 100) - 1;
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &1_u128).unwrap();
+
+        assert_eq!(x + 1, get_signal::<u128>(&wires, "z").unwrap());
+        assert!(get_signal::<u64>(&wires, "z").is_err());
+        assert!(set_signal(&mut wires, "x", &(1_u128 << 100)).is_err());
+
+        let mut wires = parse(&ripple_carry_adder(150, &[])).unwrap();
+        let x = (BigUint::one() << 150) - 1u32;
+        let y = BigUint::one() << 149;
+        set_signal(&mut wires, "x", &x).unwrap();
+        set_signal(&mut wires, "y", &y).unwrap();
+
+        assert_eq!(x + y, get_signal::<BigUint>(&wires, "z").unwrap());
+        assert_eq!(vec![] as Vec<usize>, faulty_bits(&wires).unwrap());
+    }
+
+    #[test]
+    fn bus_width_ignores_unrelated_wires() {
+        let wires = parse(indoc! {"
+            x00: 1
+            x01: 0
+            y00: 1
+            y01: 1
+
+            x00 AND y00 -> xor
+            x01 XOR y01 -> z00
+            xor OR xor -> z01
+        "}).unwrap();
+
+        assert_eq!(2, bus_width(&wires, "x").unwrap());
+        assert_eq!(2, bus_width(&wires, "z").unwrap());
+        assert!(bus_width(&parse("x00: 1\nx02: 0\n").unwrap(), "x").is_err());
+    }
+
+    #[test]
+    fn bus_with_missing_bit() {
+        let wires = parse(indoc! {"
+            x00: 1
+            y00: 1
+
+            x00 AND y00 -> z00
+            x00 OR y00 -> z02
+        "}).unwrap();
+
+        assert_eq!("Bus z is missing wire z01", get_signal::<u64>(&wires, "z").unwrap_err().to_string());
+        assert_eq!("Bus z is missing wire z01", find_swaps(&wires).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn faulty_bits_generated_adder() {
+        let wires = parse(&ripple_carry_adder(45, &[("z23", "h23"), ("a38", "s38")])).unwrap();
+
+        assert_eq!(vec![23, 38], faulty_bits(&wires).unwrap());
     }
 }
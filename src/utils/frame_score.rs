@@ -0,0 +1,136 @@
+#![allow(dead_code)]
+
+use anyhow::{ensure, Result};
+use itertools::Itertools;
+
+use crate::utils::grid::{Grid, Position};
+use crate::utils::union_find::UnionFind;
+
+/// Scores a frame of occupancy counts by how structured it looks. Every scorer is oriented so
+/// that a higher score means a less random frame.
+pub trait FrameScorer {
+    fn score(&self, frame: &Grid<usize>) -> f64;
+}
+
+impl<F> FrameScorer for F where F: Fn(&Grid<usize>) -> f64 {
+    fn score(&self, frame: &Grid<usize>) -> f64 {
+        self(frame)
+    }
+}
+
+fn occupied(frame: &Grid<usize>) -> impl Iterator<Item = (Position, usize)> + '_ {
+    frame.iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(position, &count)| (position, count))
+}
+
+/// Negated sum of the row and column variance of all occupants.
+pub struct Variance;
+
+impl FrameScorer for Variance {
+    fn score(&self, frame: &Grid<usize>) -> f64 {
+        let (total, sum_i, sum_j, sum_ii, sum_jj) = occupied(frame)
+            .map(|(Position(i, j), count)| {
+                let (i, j, count) = (i as f64, j as f64, count as f64);
+                (count, count * i, count * j, count * i * i, count * j * j)
+            })
+            .fold((0.0, 0.0, 0.0, 0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2, a.3 + b.3, a.4 + b.4));
+
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        let variance_i = sum_ii / total - (sum_i / total).powi(2);
+        let variance_j = sum_jj / total - (sum_j / total).powi(2);
+
+        -(variance_i + variance_j)
+    }
+}
+
+/// Negated Shannon entropy of the occupants over `block_size` by `block_size` blocks.
+pub struct Entropy {
+    block_size: usize,
+}
+
+impl Entropy {
+    pub fn new(block_size: usize) -> Result<Entropy> {
+        ensure!(block_size > 0, "Entropy block size must be positive");
+
+        Ok(Entropy { block_size })
+    }
+}
+
+impl FrameScorer for Entropy {
+    fn score(&self, frame: &Grid<usize>) -> f64 {
+        let block_size = self.block_size as isize;
+        let blocks = occupied(frame)
+            .map(|(Position(i, j), count)| ((i / block_size, j / block_size), count))
+            .into_grouping_map()
+            .sum();
+        let total = blocks.values().sum::<usize>() as f64;
+
+        blocks.values()
+            .map(|&count| count as f64 / total)
+            .map(|p| p * p.ln())
+            .sum()
+    }
+}
+
+/// Size of the largest 4-connected group of occupied cells.
+pub struct LargestCluster;
+
+impl FrameScorer for LargestCluster {
+    fn score(&self, frame: &Grid<usize>) -> f64 {
+        let cols = frame.cols::<isize>();
+        let index = |Position(i, j): Position| (i * cols + j) as usize;
+        let is_occupied = |position: &Position| frame.get(position).is_some_and(|&count| count > 0);
+        let mut union_find = UnionFind::new(frame.len());
+
+        for (position, _) in occupied(frame) {
+            for neighbor in [Position(position.0 + 1, position.1), Position(position.0, position.1 + 1)] {
+                if is_occupied(&neighbor) {
+                    union_find.union(index(position), index(neighbor));
+                }
+            }
+        }
+
+        occupied(frame)
+            .map(|(position, _)| union_find.component_size(index(position)))
+            .max()
+            .unwrap_or(0) as f64
+    }
+}
+
+/// Length of the longest run of occupied cells within a single row.
+pub struct LongestHorizontalRun;
+
+impl FrameScorer for LongestHorizontalRun {
+    fn score(&self, frame: &Grid<usize>) -> f64 {
+        let mut longest = 0;
+        let mut current = 0;
+
+        for (Position(_, j), &count) in frame {
+            if j == 0 {
+                current = 0;
+            }
+
+            current = if count > 0 { current + 1 } else { 0 };
+            longest = longest.max(current);
+        }
+
+        longest as f64
+    }
+}
+
+/// Scores every `(label, frame)` pair and returns the `k` best labels with their scores, best
+/// first. Ties keep the order in which the frames were produced.
+pub fn top_k<L>(frames: impl IntoIterator<Item = (L, Grid<usize>)>, scorer: &impl FrameScorer, k: usize) -> Vec<(L, f64)> {
+    frames.into_iter()
+        .map(|(label, frame)| (label, scorer.score(&frame)))
+        .enumerate()
+        .k_smallest_by(k, |(index_a, (_, score_a)), (index_b, (_, score_b))| {
+            score_b.total_cmp(score_a).then(index_a.cmp(index_b))
+        })
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
@@ -0,0 +1,525 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use hashbrown::HashMap;
+use itertools::Itertools;
+
+/// Directed graph over arbitrary node values. Nodes are stored in insertion order and referred to
+/// internally by index, which keeps every algorithm below deterministic.
+#[derive(Clone, Debug)]
+pub struct Graph<Node> {
+    nodes: Vec<Node>,
+    indices: HashMap<Node, usize>,
+    edges: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cycle<Node>(pub Vec<Node>);
+
+impl<Node> Display for Cycle<Node> where Node: Debug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Graph contains a cycle: {}", self.0.iter().map(|node| format!("{node:?}")).join(" -> "))
+    }
+}
+
+impl<Node> std::error::Error for Cycle<Node> where Node: Debug {}
+
+impl<Node> Default for Graph<Node> {
+    fn default() -> Self {
+        Graph {
+            nodes: vec![],
+            indices: HashMap::new(),
+            edges: vec![],
+        }
+    }
+}
+
+impl<Node> FromIterator<(Node, Node)> for Graph<Node> where Node: Copy + Eq + Hash {
+    fn from_iter<I: IntoIterator<Item = (Node, Node)>>(edges: I) -> Self {
+        let mut graph = Graph::new();
+
+        for (from, to) in edges {
+            graph.add_edge(from, to);
+        }
+
+        graph
+    }
+}
+
+impl<Node> Graph<Node> where Node: Copy + Eq + Hash {
+    pub fn new() -> Graph<Node> {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: Node) -> usize {
+        *self.indices.entry(node).or_insert_with(|| {
+            self.nodes.push(node);
+            self.edges.push(vec![]);
+            self.nodes.len() - 1
+        })
+    }
+
+    /// Adds an edge `from -> to`, adding either node if missing. Duplicate edges are ignored.
+    pub fn add_edge(&mut self, from: Node, to: Node) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+
+        if !self.edges[from].contains(&to) {
+            self.edges[from].push(to);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn contains_node(&self, node: &Node) -> bool {
+        self.indices.contains_key(node)
+    }
+
+    pub fn contains_edge(&self, from: &Node, to: &Node) -> bool {
+        match (self.indices.get(from), self.indices.get(to)) {
+            (Some(from), Some(to)) => self.edges[*from].contains(to),
+            _ => false,
+        }
+    }
+
+    pub fn successors(&self, node: &Node) -> impl Iterator<Item = Node> + '_ {
+        self.indices
+            .get(node)
+            .into_iter()
+            .flat_map(|&index| self.edges[index].iter().map(|&successor| self.nodes[successor]))
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (Node, Node)> + '_ {
+        self.edges
+            .iter()
+            .enumerate()
+            .flat_map(move |(from, successors)| successors.iter().map(move |&to| (self.nodes[from], self.nodes[to])))
+    }
+
+    /// Returns only the nodes in `keep`, along with the edges between them.
+    pub fn subgraph(&self, keep: impl Fn(&Node) -> bool) -> Graph<Node> {
+        let mut graph = Graph::new();
+
+        for node in self.nodes.iter().filter(|node| keep(node)) {
+            graph.add_node(*node);
+        }
+
+        for (from, to) in self.edges().filter(|(from, to)| keep(from) && keep(to)) {
+            graph.add_edge(from, to);
+        }
+
+        graph
+    }
+
+    fn topological_order(&self) -> Result<Vec<usize>, Vec<usize>> {
+        let mut in_degrees = vec![0; self.len()];
+
+        for &to in self.edges.iter().flatten() {
+            in_degrees[to] += 1;
+        }
+
+        let mut queue = (0..self.len()).filter(|&node| in_degrees[node] == 0).collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(current) = queue.pop_front() {
+            order.push(current);
+
+            for &successor in &self.edges[current] {
+                in_degrees[successor] -= 1;
+
+                if in_degrees[successor] == 0 {
+                    queue.push_back(successor);
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            return Ok(order);
+        }
+
+        // Every node left with a non-zero in-degree has a predecessor that is also left, so walking
+        // backwards from any of them must eventually revisit a node.
+        let mut predecessors = vec![None; self.len()];
+
+        for (from, successors) in self.edges.iter().enumerate() {
+            for &to in successors {
+                if in_degrees[from] > 0 && in_degrees[to] > 0 {
+                    predecessors[to] = Some(from);
+                }
+            }
+        }
+
+        let start = (0..self.len()).find(|&node| in_degrees[node] > 0).unwrap();
+        let mut visited = vec![None; self.len()];
+        let mut path = vec![];
+        let mut current = start;
+
+        while visited[current].is_none() {
+            visited[current] = Some(path.len());
+            path.push(current);
+            current = predecessors[current].unwrap();
+        }
+
+        let mut cycle = path.split_off(visited[current].unwrap());
+        cycle.reverse();
+
+        Err(cycle)
+    }
+
+    /// Orders the nodes so that every edge points forward, using Kahn's algorithm. Ties are broken
+    /// by insertion order. If the graph is cyclic, one of its cycles is returned instead.
+    pub fn topological_sort(&self) -> Result<Vec<Node>, Cycle<Node>> {
+        self.topological_order()
+            .map(|order| order.into_iter().map(|node| self.nodes[node]).collect())
+            .map_err(|cycle| Cycle(cycle.into_iter().map(|node| self.nodes[node]).collect()))
+    }
+
+    /// Finds the strongly connected components using Tarjan's algorithm. Components are returned
+    /// in reverse topological order of the condensed graph.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Node>> {
+        let mut indices = vec![None; self.len()];
+        let mut low_links = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = vec![];
+        let mut next_index = 0;
+        let mut components = vec![];
+
+        for root in 0..self.len() {
+            if indices[root].is_some() {
+                continue;
+            }
+
+            let mut call_stack = vec![(root, 0)];
+
+            while let Some((current, edge)) = call_stack.pop() {
+                if edge == 0 {
+                    indices[current] = Some(next_index);
+                    low_links[current] = next_index;
+                    next_index += 1;
+                    stack.push(current);
+                    on_stack[current] = true;
+                }
+
+                if let Some(&successor) = self.edges[current].get(edge) {
+                    call_stack.push((current, edge + 1));
+
+                    match indices[successor] {
+                        None => call_stack.push((successor, 0)),
+                        Some(index) if on_stack[successor] => {
+                            low_links[current] = low_links[current].min(index);
+                        }
+                        Some(_) => {}
+                    }
+
+                    continue;
+                }
+
+                if Some(low_links[current]) == indices[current] {
+                    let mut component = vec![];
+
+                    loop {
+                        let node = stack.pop().unwrap();
+                        on_stack[node] = false;
+                        component.push(self.nodes[node]);
+
+                        if node == current { break; }
+                    }
+
+                    component.reverse();
+                    components.push(component);
+                }
+
+                if let Some(&(parent, _)) = call_stack.last() {
+                    low_links[parent] = low_links[parent].min(low_links[current]);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Removes every edge `a -> c` that is implied by a longer path `a -> b -> ... -> c`. Only
+    /// defined for acyclic graphs, so a cycle is returned if there is one.
+    pub fn transitive_reduction(&self) -> Result<Graph<Node>, Cycle<Node>> {
+        let order = self.topological_order()
+            .map_err(|cycle| Cycle(cycle.into_iter().map(|node| self.nodes[node]).collect()))?;
+
+        let mut positions = vec![0; self.len()];
+
+        for (position, &node) in order.iter().enumerate() {
+            positions[node] = position;
+        }
+
+        let words = self.len().div_ceil(64);
+        let mut reachable = vec![vec![0u64; words]; self.len()];
+        let mut graph = Graph::new();
+
+        for &node in &self.nodes {
+            graph.add_node(node);
+        }
+
+        for &current in order.iter().rev() {
+            let mut covered = vec![0u64; words];
+
+            for &successor in self.edges[current].iter().sorted_by_key(|&&successor| positions[successor]) {
+                if covered[successor / 64] & (1 << (successor % 64)) == 0 {
+                    graph.add_edge(self.nodes[current], self.nodes[successor]);
+
+                    for (word, reached) in covered.iter_mut().zip(&reachable[successor]) {
+                        *word |= reached;
+                    }
+                }
+            }
+
+            covered[current / 64] |= 1 << (current % 64);
+            reachable[current] = covered;
+        }
+
+        Ok(graph)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> BitSet {
+        BitSet(vec![0; len.div_ceil(64)])
+    }
+
+    fn full(len: usize) -> BitSet {
+        let mut set = BitSet::new(len);
+
+        for element in 0..len {
+            set.insert(element);
+        }
+
+        set
+    }
+
+    fn insert(&mut self, element: usize) {
+        if element / 64 >= self.0.len() {
+            self.0.resize(element / 64 + 1, 0);
+        }
+
+        self.0[element / 64] |= 1 << (element % 64);
+    }
+
+    fn remove(&mut self, element: usize) {
+        if let Some(word) = self.0.get_mut(element / 64) {
+            *word &= !(1 << (element % 64));
+        }
+    }
+
+    /// Removes every element less than or equal to `element`.
+    fn remove_through(&mut self, element: usize) {
+        for (i, word) in self.0.iter_mut().enumerate().take(element / 64 + 1) {
+            if i < element / 64 {
+                *word = 0;
+            } else {
+                *word &= !(u64::MAX >> (63 - element % 64));
+            }
+        }
+    }
+
+    fn contains(&self, element: usize) -> bool {
+        self.0.get(element / 64).is_some_and(|word| word & (1 << (element % 64)) != 0)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    fn count(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn intersection(&self, other: &BitSet) -> BitSet {
+        BitSet(self.0.iter().zip(other.0.iter().chain(std::iter::repeat(&0))).map(|(a, b)| a & b).collect())
+    }
+
+    fn difference(&self, other: &BitSet) -> BitSet {
+        BitSet(self.0.iter().zip(other.0.iter().chain(std::iter::repeat(&0))).map(|(a, b)| a & !b).collect())
+    }
+
+    fn union(&self, other: &BitSet) -> BitSet {
+        let len = self.0.len().max(other.0.len());
+        BitSet((0..len).map(|i| self.0.get(i).unwrap_or(&0) | other.0.get(i).unwrap_or(&0)).collect())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64).filter(move |bit| word & (1 << bit) != 0).map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+/// Undirected graph over string names, interned to dense `usize` ids in insertion order.
+#[derive(Clone, Debug, Default)]
+pub struct UndirectedGraph {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    adjacency: Vec<BitSet>,
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for UndirectedGraph {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(edges: I) -> Self {
+        let mut graph = UndirectedGraph::new();
+
+        for (a, b) in edges {
+            graph.add_edge(a, b);
+        }
+
+        graph
+    }
+}
+
+impl UndirectedGraph {
+    pub fn new() -> UndirectedGraph {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), self.names.len() - 1);
+        self.adjacency.push(BitSet::default());
+        self.names.len() - 1
+    }
+
+    pub fn add_edge(&mut self, a: &str, b: &str) {
+        let a = self.intern(a);
+        let b = self.intern(b);
+
+        if a != b {
+            self.adjacency[a].insert(b);
+            self.adjacency[b].insert(a);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    pub fn names<'a>(&'a self, ids: &'a [usize]) -> impl Iterator<Item = &'a str> + 'a {
+        ids.iter().map(|&id| self.name(id))
+    }
+
+    pub fn contains_edge(&self, a: usize, b: usize) -> bool {
+        self.adjacency[a].contains(b)
+    }
+
+    pub fn neighbors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.adjacency[id].iter()
+    }
+
+    /// Enumerates every maximal clique using Bron–Kerbosch with Tomita pivoting. Each clique is
+    /// returned as ids in ascending order.
+    pub fn maximal_cliques(&self) -> Vec<Vec<usize>> {
+        let mut cliques = vec![];
+
+        if self.is_empty() {
+            return cliques;
+        }
+
+        self.bron_kerbosch(&mut vec![], BitSet::full(self.len()), BitSet::new(self.len()), &mut cliques);
+
+        cliques
+    }
+
+    fn bron_kerbosch(&self, clique: &mut Vec<usize>, mut candidates: BitSet, mut excluded: BitSet, cliques: &mut Vec<Vec<usize>>) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                cliques.push(clique.iter().copied().sorted().collect());
+            }
+
+            return;
+        }
+
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|&node| candidates.intersection(&self.adjacency[node]).count())
+            .unwrap();
+
+        for node in candidates.difference(&self.adjacency[pivot]).iter().collect_vec() {
+            clique.push(node);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(&self.adjacency[node]),
+                excluded.intersection(&self.adjacency[node]),
+                cliques,
+            );
+            clique.pop();
+
+            candidates.remove(node);
+            excluded.insert(node);
+        }
+    }
+
+    /// Returns a largest clique, preferring the lexicographically smallest ids on ties.
+    pub fn maximum_clique(&self) -> Option<Vec<usize>> {
+        self.maximal_cliques()
+            .into_iter()
+            .min_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)))
+    }
+
+    /// Enumerates every clique of exactly `k` nodes, as ascending ids, in lexicographic order.
+    pub fn k_cliques(&self, k: usize) -> Vec<Vec<usize>> {
+        let mut cliques = vec![];
+
+        if k > 0 {
+            self.extend_k_clique(&mut vec![], BitSet::full(self.len()), k, &mut cliques);
+        }
+
+        cliques
+    }
+
+    fn extend_k_clique(&self, clique: &mut Vec<usize>, candidates: BitSet, k: usize, cliques: &mut Vec<Vec<usize>>) {
+        if clique.len() == k {
+            cliques.push(clique.clone());
+            return;
+        }
+
+        if clique.len() + candidates.count() < k {
+            return;
+        }
+
+        for node in candidates.iter() {
+            let mut next_candidates = candidates.intersection(&self.adjacency[node]);
+            next_candidates.remove_through(node);
+
+            clique.push(node);
+            self.extend_k_clique(clique, next_candidates, k, cliques);
+            clique.pop();
+        }
+    }
+}
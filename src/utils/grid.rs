@@ -202,6 +202,89 @@ impl<T> Grid<T> where T: Clone {
     pub fn set(&mut self, &Position(i, j): &Position, value: T) {
         self[(i, j)] = value;
     }
+
+    pub fn iter(&self) -> GridRefIntoIter<'_, T> {
+        self.into_iter()
+    }
+
+    pub fn render(&self, cell: impl Fn(Position, &T) -> char) -> String {
+        let mut output = String::with_capacity(self.len::<usize>() + self.rows::<usize>());
+
+        for i in 0..self.rows::<isize>() {
+            for j in 0..self.cols::<isize>() {
+                output.push(cell(Position(i, j), &self[(i, j)]));
+            }
+
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Row-major RGB bytes of the grid, drawing every cell as a `scale` by `scale` square.
+    fn rgb_pixels(&self, scale: usize, color: impl Fn(Position, &T) -> [u8; 3]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(self.len::<usize>() * scale * scale * 3);
+
+        for i in 0..self.rows::<isize>() {
+            let row = (0..self.cols::<isize>())
+                .map(|j| color(Position(i, j), &self[(i, j)]))
+                .collect_vec();
+
+            for _ in 0..scale {
+                for rgb in &row {
+                    for _ in 0..scale {
+                        output.extend_from_slice(rgb);
+                    }
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Renders the grid as a binary PPM (P6) image, drawing every cell as a `scale` by `scale`
+    /// square.
+    pub fn render_ppm(&self, scale: usize, color: impl Fn(Position, &T) -> [u8; 3]) -> Vec<u8> {
+        let width = self.cols::<usize>() * scale;
+        let height = self.rows::<usize>() * scale;
+        let mut output = format!("P6\n{width} {height}\n255\n").into_bytes();
+        output.extend(self.rgb_pixels(scale, color));
+
+        output
+    }
+
+    /// Renders the grid as a plain PBM (P1) bitmap with one pixel per cell. Rows are wrapped so
+    /// that no line is longer than the 70 characters the format allows.
+    pub fn render_pbm(&self, is_set: impl Fn(Position, &T) -> bool) -> String {
+        let mut output = format!("P1\n{} {}\n", self.cols::<usize>(), self.rows::<usize>());
+
+        for i in 0..self.rows::<isize>() {
+            let bits = (0..self.cols::<isize>())
+                .map(|j| if is_set(Position(i, j), &self[(i, j)]) { '1' } else { '0' })
+                .collect_vec();
+
+            for line in bits.chunks(70) {
+                output.extend(line);
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    /// Renders the grid as an RGB PNG image, drawing every cell as a `scale` by `scale` square.
+    pub fn render_png(&self, scale: usize, color: impl Fn(Position, &T) -> [u8; 3]) -> Result<Vec<u8>> {
+        let width = self.cols::<usize>() * scale;
+        let height = self.rows::<usize>() * scale;
+        let mut output = Vec::new();
+
+        let mut encoder = png::Encoder::new(&mut output, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb_pixels(scale, color))?;
+
+        Ok(output)
+    }
 }
 
 impl<T, S> Index<(S, S)> for Grid<T> where T: Clone, S: Into<GridSize> {
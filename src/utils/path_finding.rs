@@ -7,6 +7,112 @@ use std::hash::Hash;
 use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 
+use crate::utils::grid::{Grid, Position};
+
+/// Hook for watching a search as it runs. Every method defaults to doing nothing, so an observer
+/// only needs to implement the events it cares about.
+pub trait SearchObserver<SearchNode> {
+    fn on_expand(&mut self, _node: SearchNode, _distance: usize, _queue_len: usize) {}
+    fn on_relax(&mut self, _from: SearchNode, _to: SearchNode, _distance: usize) {}
+    fn on_target(&mut self, _node: SearchNode, _distance: usize) {}
+}
+
+impl<SearchNode> SearchObserver<SearchNode> for () {}
+
+impl<SearchNode, A, B> SearchObserver<SearchNode> for (A, B) where
+    SearchNode: Copy,
+    A: SearchObserver<SearchNode>,
+    B: SearchObserver<SearchNode>,
+{
+    fn on_expand(&mut self, node: SearchNode, distance: usize, queue_len: usize) {
+        self.0.on_expand(node, distance, queue_len);
+        self.1.on_expand(node, distance, queue_len);
+    }
+
+    fn on_relax(&mut self, from: SearchNode, to: SearchNode, distance: usize) {
+        self.0.on_relax(from, to, distance);
+        self.1.on_relax(from, to, distance);
+    }
+
+    fn on_target(&mut self, node: SearchNode, distance: usize) {
+        self.0.on_target(node, distance);
+        self.1.on_target(node, distance);
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ExpansionCounter {
+    pub expansions: usize,
+    pub relaxations: usize,
+    pub targets: usize,
+}
+
+impl<SearchNode> SearchObserver<SearchNode> for ExpansionCounter {
+    fn on_expand(&mut self, _node: SearchNode, _distance: usize, _queue_len: usize) {
+        self.expansions += 1;
+    }
+
+    fn on_relax(&mut self, _from: SearchNode, _to: SearchNode, _distance: usize) {
+        self.relaxations += 1;
+    }
+
+    fn on_target(&mut self, _node: SearchNode, _distance: usize) {
+        self.targets += 1;
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct MaxQueueSize(pub usize);
+
+impl<SearchNode> SearchObserver<SearchNode> for MaxQueueSize {
+    fn on_expand(&mut self, _node: SearchNode, _distance: usize, queue_len: usize) {
+        self.0 = self.0.max(queue_len);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpansionOrder<SearchNode>(pub Vec<SearchNode>);
+
+impl<SearchNode> Default for ExpansionOrder<SearchNode> {
+    fn default() -> Self {
+        ExpansionOrder(vec![])
+    }
+}
+
+impl<SearchNode> SearchObserver<SearchNode> for ExpansionOrder<SearchNode> {
+    fn on_expand(&mut self, node: SearchNode, _distance: usize, _queue_len: usize) {
+        self.0.push(node);
+    }
+}
+
+impl<SearchNode> ExpansionOrder<SearchNode> where SearchNode: Copy {
+    /// Draws the grid with every expanded cell replaced by a character from `0-9a-z` showing how
+    /// early in the search it was first expanded. Unexpanded cells are drawn with `cell`.
+    pub fn render<T>(
+        &self,
+        grid: &Grid<T>,
+        position: impl Fn(SearchNode) -> Position,
+        cell: impl Fn(&T) -> char,
+    ) -> String where
+        T: Clone,
+    {
+        const RAMP: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+        let mut first_expansions = HashMap::new();
+
+        for (step, node) in self.0.iter().enumerate() {
+            first_expansions.entry(position(*node)).or_insert(step);
+        }
+
+        let steps = self.0.len().max(1);
+
+        grid.render(|p, t| match first_expansions.get(&p) {
+            Some(step) => RAMP[step * RAMP.len() / steps] as char,
+            None => cell(t),
+        })
+    }
+}
+
 type Distances<SearchNode> = HashMap<SearchNode, (usize, Vec<SearchNode>)>;
 pub type PathMap<SearchNode> = HashMap<SearchNode, Vec<SearchNode>>;
 
@@ -42,12 +148,25 @@ pub fn shortest_paths_to_target<SearchNode, IsTargetFn> (
 ) -> Option<(usize, PathMap<SearchNode>)> where
     SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
     IsTargetFn: Fn(SearchNode) -> bool + Copy,
+{
+    shortest_paths_to_target_observed(source, neighbors, is_target, &mut ())
+}
+
+pub fn shortest_paths_to_target_observed<SearchNode, IsTargetFn> (
+    source: SearchNode,
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    is_target: IsTargetFn,
+    observer: &mut impl SearchObserver<SearchNode>,
+) -> Option<(usize, PathMap<SearchNode>)> where
+    SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
+    IsTargetFn: Fn(SearchNode) -> bool + Copy,
 {
     let (distances, reached_targets) = distances_impl(
         source,
         neighbors,
         is_target,
-        true
+        true,
+        observer,
     );
 
     if reached_targets.is_empty() {
@@ -80,12 +199,25 @@ pub fn distance_to_target<SearchNode, IsTargetFn> (
 ) -> Option<usize> where
     SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
     IsTargetFn: Fn(SearchNode) -> bool + Copy,
+{
+    distance_to_target_observed(source, neighbors, is_target, &mut ())
+}
+
+pub fn distance_to_target_observed<SearchNode, IsTargetFn> (
+    source: SearchNode,
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    is_target: IsTargetFn,
+    observer: &mut impl SearchObserver<SearchNode>,
+) -> Option<usize> where
+    SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
+    IsTargetFn: Fn(SearchNode) -> bool + Copy,
 {
     let (distances, targets_reached) = distances_impl(
         source,
         neighbors,
         is_target,
         false,
+        observer,
     );
 
     if targets_reached.is_empty() {
@@ -104,6 +236,7 @@ fn distances_impl<SearchNode, IsTargetFn> (
     neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
     is_target: IsTargetFn,
     break_at_target: bool,
+    observer: &mut impl SearchObserver<SearchNode>,
 ) -> (Distances<SearchNode>, Vec<SearchNode>) where
     SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
     IsTargetFn: Fn(SearchNode) -> bool + Copy,
@@ -113,7 +246,14 @@ fn distances_impl<SearchNode, IsTargetFn> (
     let mut targets_reached = vec![];
 
     while let Some((Reverse(distance), current)) = queue.pop() {
+        if distance > distances[&current].0 {
+            continue;
+        }
+
+        observer.on_expand(current, distance, queue.len() + 1);
+
         if is_target(current) {
+            observer.on_target(current, distance);
             targets_reached.push(current);
 
             if break_at_target {
@@ -131,6 +271,7 @@ fn distances_impl<SearchNode, IsTargetFn> (
                     *neighbor_distance = distance + cost;
                     *neighbor_sources = vec![current];
                     queue.push((Reverse(*neighbor_distance), neighbor));
+                    observer.on_relax(current, neighbor, *neighbor_distance);
                 }
                 Ordering::Equal => {
                     neighbor_sources.push(current);
@@ -142,3 +283,226 @@ fn distances_impl<SearchNode, IsTargetFn> (
 
     (distances, targets_reached)
 }
+
+fn shortest_path_avoiding<SearchNode, IsTargetFn> (
+    source: SearchNode,
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    is_target: IsTargetFn,
+    blocked_nodes: &HashSet<SearchNode>,
+    blocked_edges: &HashSet<(SearchNode, SearchNode)>,
+) -> Option<(usize, Vec<SearchNode>)> where
+    SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
+    IsTargetFn: Fn(SearchNode) -> bool + Copy,
+{
+    let mut distances: HashMap<_, (usize, Option<SearchNode>)> = HashMap::from([(source, (0, None))]);
+    let mut queue = BinaryHeap::from([(Reverse(0), source)]);
+
+    while let Some((Reverse(distance), current)) = queue.pop() {
+        if distance > distances[&current].0 {
+            continue;
+        }
+
+        if is_target(current) {
+            let mut path = vec![current];
+
+            while let Some(&(_, Some(previous))) = distances.get(path.last()?) {
+                path.push(previous);
+            }
+
+            path.reverse();
+            return Some((distance, path));
+        }
+
+        for (neighbor, cost) in neighbors(current) {
+            if blocked_nodes.contains(&neighbor) || blocked_edges.contains(&(current, neighbor)) {
+                continue;
+            }
+
+            let (neighbor_distance, neighbor_source) = distances
+                .entry(neighbor)
+                .or_insert((usize::MAX, None));
+
+            if distance + cost < *neighbor_distance {
+                *neighbor_distance = distance + cost;
+                *neighbor_source = Some(current);
+                queue.push((Reverse(*neighbor_distance), neighbor));
+            }
+        }
+    }
+
+    None
+}
+
+fn edge_cost<SearchNode>(
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    from: SearchNode,
+    to: SearchNode,
+) -> Option<usize> where
+    SearchNode: Copy + Clone + PartialEq,
+{
+    neighbors(from)
+        .into_iter()
+        .filter(|(neighbor, _)| *neighbor == to)
+        .map(|(_, cost)| cost)
+        .min()
+}
+
+/// Finds up to `k` distinct loopless paths from `source` to a target, in order of increasing
+/// distance, using Yen's algorithm. Targets are treated as terminal, so no returned path passes
+/// through a target before its last node.
+pub fn k_shortest_paths<SearchNode, IsTargetFn> (
+    source: SearchNode,
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    is_target: IsTargetFn,
+    k: usize,
+) -> Vec<(usize, Vec<SearchNode>)> where
+    SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
+    IsTargetFn: Fn(SearchNode) -> bool + Copy,
+{
+    if k == 0 {
+        return vec![];
+    }
+
+    let Some(shortest) = shortest_path_avoiding(source, &neighbors, is_target, &HashSet::new(), &HashSet::new()) else {
+        return vec![];
+    };
+
+    let mut paths = vec![shortest];
+    let mut candidates = BinaryHeap::new();
+    let mut seen: HashSet<Vec<SearchNode>> = HashSet::from([paths[0].1.clone()]);
+
+    while paths.len() < k {
+        let (_, previous_path) = paths.last().unwrap();
+        let mut root_distance = 0;
+
+        for i in 0..previous_path.len() - 1 {
+            let spur_node = previous_path[i];
+            let root = &previous_path[..=i];
+
+            let blocked_edges = paths
+                .iter()
+                .filter(|(_, path)| path.len() > i + 1 && &path[..=i] == root)
+                .map(|(_, path)| (path[i], path[i + 1]))
+                .collect::<HashSet<_>>();
+            let blocked_nodes = root[..i].iter().copied().collect::<HashSet<_>>();
+
+            if let Some((spur_distance, spur_path)) = shortest_path_avoiding(spur_node, &neighbors, is_target, &blocked_nodes, &blocked_edges) {
+                let path = root[..i].iter().copied().chain(spur_path).collect_vec();
+
+                if seen.insert(path.clone()) {
+                    candidates.push((Reverse(root_distance + spur_distance), path));
+                }
+            }
+
+            let Some(cost) = edge_cost(&neighbors, spur_node, previous_path[i + 1]) else {
+                break;
+            };
+            root_distance += cost;
+        }
+
+        let Some((Reverse(distance), path)) = candidates.pop() else {
+            break;
+        };
+
+        paths.push((distance, path));
+    }
+
+    paths
+}
+
+/// Finds every loopless path from `source` to a target whose distance is at most `slack` more
+/// than the shortest distance, sorted by distance. Targets are treated as terminal.
+pub fn paths_within_slack<SearchNode, IsTargetFn> (
+    source: SearchNode,
+    neighbors: impl Fn(SearchNode) -> Vec<(SearchNode, usize)>,
+    is_target: IsTargetFn,
+    slack: usize,
+) -> Vec<(usize, Vec<SearchNode>)> where
+    SearchNode: Copy + Clone + PartialEq + PartialOrd + Ord + Hash,
+    IsTargetFn: Fn(SearchNode) -> bool + Copy,
+{
+    let mut reverse_edges: HashMap<SearchNode, Vec<(SearchNode, usize)>> = HashMap::new();
+    let mut reachable: HashSet<SearchNode> = HashSet::from([source]);
+    let mut queue = VecDeque::from([source]);
+    let mut targets = vec![];
+
+    while let Some(current) = queue.pop_front() {
+        if is_target(current) {
+            targets.push(current);
+            continue;
+        }
+
+        for (neighbor, cost) in neighbors(current) {
+            reverse_edges.entry(neighbor).or_default().push((current, cost));
+
+            if reachable.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    let mut remaining = targets.iter().map(|target| (*target, 0)).collect::<HashMap<_, usize>>();
+    let mut queue = BinaryHeap::from_iter(targets.iter().map(|target| (Reverse(0), *target)));
+
+    while let Some((Reverse(distance), current)) = queue.pop() {
+        if distance > remaining[&current] {
+            continue;
+        }
+
+        for &(previous, cost) in reverse_edges.get(&current).unwrap_or(&vec![]) {
+            if is_target(previous) {
+                continue;
+            }
+
+            let previous_distance = remaining.entry(previous).or_insert(usize::MAX);
+
+            if distance + cost < *previous_distance {
+                *previous_distance = distance + cost;
+                queue.push((Reverse(*previous_distance), previous));
+            }
+        }
+    }
+
+    let Some(&shortest) = remaining.get(&source) else {
+        return vec![];
+    };
+
+    let budget = shortest + slack;
+    let mut paths = vec![];
+    let mut path = vec![];
+    let mut on_path = HashSet::new();
+    let mut stack = vec![(source, 0, 0)];
+
+    while let Some((current, distance, depth)) = stack.pop() {
+        for removed in path.drain(depth..) {
+            on_path.remove(&removed);
+        }
+
+        path.push(current);
+        on_path.insert(current);
+
+        if is_target(current) {
+            paths.push((distance, path.clone()));
+            continue;
+        }
+
+        for (neighbor, cost) in neighbors(current) {
+            if on_path.contains(&neighbor) {
+                continue;
+            }
+
+            if let Some(neighbor_remaining) = remaining.get(&neighbor) {
+                if distance + cost + neighbor_remaining <= budget {
+                    stack.push((neighbor, distance + cost, depth + 1));
+                }
+            }
+        }
+    }
+
+    paths.sort();
+
+    let mut seen = HashSet::new();
+    paths.retain(|(_, path)| seen.insert(path.clone()));
+
+    paths
+}
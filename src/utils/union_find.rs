@@ -0,0 +1,140 @@
+#![allow(dead_code)]
+
+use crate::utils::grid::{Direction, Grid, IntoEnumIterator, Position};
+
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+    components: usize,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> UnionFind {
+        UnionFind {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+            components: len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    pub fn find(&mut self, mut element: usize) -> usize {
+        while self.parents[element] != element {
+            self.parents[element] = self.parents[self.parents[element]];
+            element = self.parents[element];
+        }
+
+        element
+    }
+
+    /// Merges the sets containing `a` and `b`, returning `false` if they were already the same set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b {
+            return false;
+        }
+
+        let (large, small) = if self.sizes[a] >= self.sizes[b] { (a, b) } else { (b, a) };
+        self.parents[small] = large;
+        self.sizes[large] += self.sizes[small];
+        self.components -= 1;
+
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn component_size(&mut self, element: usize) -> usize {
+        let root = self.find(element);
+        self.sizes[root]
+    }
+}
+
+/// Blocks the cells in `blocked` one at a time and returns the index of the first one that leaves
+/// `source` and `target` disconnected. Cells for which `is_free` is false are always blocked.
+///
+/// The blocks are removed in reverse order from a fully blocked grid, so the whole query is a
+/// single pass over a union-find. Returns `None` if `source` and `target` stay connected, or are
+/// never connected to begin with.
+pub fn first_disconnecting_block<T>(
+    grid: &Grid<T>,
+    is_free: impl Fn(&T) -> bool,
+    blocked: &[Position],
+    source: Position,
+    target: Position,
+) -> Option<usize> where
+    T: Clone,
+{
+    let cols = grid.cols::<isize>();
+    let index = |Position(i, j): Position| (i * cols + j) as usize;
+
+    grid.get(&source)?;
+    grid.get(&target)?;
+
+    let mut block_counts = vec![0usize; grid.len()];
+
+    for position in blocked {
+        grid.get(position)?;
+        block_counts[index(*position)] += 1;
+    }
+
+    let is_open = |block_counts: &[usize], position: Position| {
+        grid.get(&position).is_some_and(|cell| is_free(cell) && block_counts[index(position)] == 0)
+    };
+
+    let mut union_find = UnionFind::new(grid.len());
+
+    let open = |union_find: &mut UnionFind, block_counts: &[usize], position: Position| {
+        for neighbor in Direction::iter().map(|direction| position.step(direction)) {
+            if is_open(block_counts, neighbor) {
+                union_find.union(index(position), index(neighbor));
+            }
+        }
+    };
+
+    for (position, _) in grid {
+        if is_open(&block_counts, position) {
+            open(&mut union_find, &block_counts, position);
+        }
+    }
+
+    let is_connected = |union_find: &mut UnionFind, block_counts: &[usize]| {
+        is_open(block_counts, source)
+            && is_open(block_counts, target)
+            && union_find.connected(index(source), index(target))
+    };
+
+    if is_connected(&mut union_find, &block_counts) {
+        return None;
+    }
+
+    for (time, &position) in blocked.iter().enumerate().rev() {
+        block_counts[index(position)] -= 1;
+
+        if is_open(&block_counts, position) {
+            open(&mut union_find, &block_counts, position);
+
+            if is_connected(&mut union_find, &block_counts) {
+                return Some(time);
+            }
+        }
+    }
+
+    None
+}